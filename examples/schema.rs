@@ -4,8 +4,14 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use miaw_limit_order::msg::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, LastOrderIdResponse, OrderResponse, OrdersResponse,
-    QueryMsg,
+    AllowanceCheckResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, ExecutorStatsResponse,
+    FeeTokenInfoResponse, FillsResponse, HasOpenOrdersResponse, InstantiateMsg,
+    LastOrderIdResponse, MaxFillWithinSpreadResponse, MedianPriceResponse, OptimalFillSetResponse,
+    OrderCountsByPairResponse, OrderGroupResponse, OrderResponse, OrderStatsResponse,
+    OrderWithBidderContextResponse, OrdersResponse, PairStatsResponse, PauseStateResponse,
+    PreviewExecutionResponse, PreviewSubmitResponse, PrioritizedOrdersResponse, QueryMsg,
+    RecentResponse, SignedOrderPayload, TopBiddersResponse, TotalEscrowedFeesResponse,
+    UnderwaterOrdersResponse,
 };
 
 fn main() {
@@ -16,9 +22,32 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(SignedOrderPayload), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(OrderResponse), &out_dir);
     export_schema(&schema_for!(OrdersResponse), &out_dir);
     export_schema(&schema_for!(LastOrderIdResponse), &out_dir);
+    export_schema(&schema_for!(OrderStatsResponse), &out_dir);
+    export_schema(&schema_for!(OrderGroupResponse), &out_dir);
+    export_schema(&schema_for!(ExecutorStatsResponse), &out_dir);
+    export_schema(&schema_for!(HasOpenOrdersResponse), &out_dir);
+    export_schema(&schema_for!(PrioritizedOrdersResponse), &out_dir);
+    export_schema(&schema_for!(UnderwaterOrdersResponse), &out_dir);
+    export_schema(&schema_for!(OrderWithBidderContextResponse), &out_dir);
+    export_schema(&schema_for!(MedianPriceResponse), &out_dir);
+    export_schema(&schema_for!(RecentResponse), &out_dir);
+    export_schema(&schema_for!(TotalEscrowedFeesResponse), &out_dir);
+    export_schema(&schema_for!(TopBiddersResponse), &out_dir);
+    export_schema(&schema_for!(FeeTokenInfoResponse), &out_dir);
+    export_schema(&schema_for!(FillsResponse), &out_dir);
+    export_schema(&schema_for!(MaxFillWithinSpreadResponse), &out_dir);
+    export_schema(&schema_for!(OptimalFillSetResponse), &out_dir);
+    export_schema(&schema_for!(AllowanceCheckResponse), &out_dir);
+    export_schema(&schema_for!(PauseStateResponse), &out_dir);
+    export_schema(&schema_for!(PairStatsResponse), &out_dir);
+    export_schema(&schema_for!(OrderCountsByPairResponse), &out_dir);
+    export_schema(&schema_for!(PreviewSubmitResponse), &out_dir);
+    export_schema(&schema_for!(PreviewExecutionResponse), &out_dir);
 }
@@ -1,27 +1,157 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, from_binary, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
 
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
-use crate::order::{cancel_order, execute_order, submit_order};
-use crate::query::{query_config, query_last_order_id, query_order, query_orders};
-use crate::state::{Config, CONFIG, LAST_ORDER_ID};
+use crate::msg::{Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::order::{
+    build_initial_order, cancel_order, cancel_order_reply, cancel_user_pair_orders, claim_rebates,
+    cleanup_expired_orders, execute_order, execute_order_reply, fill_hook_reply, heartbeat,
+    retarget_order, submit_order, submit_orders, submit_signed_order, top_up_fee, update_order,
+    CANCEL_ORDER_REPLY_ID, EXECUTE_ORDER_REPLY_ID, FILL_HOOK_REPLY_ID,
+};
+use crate::order_group::{cancel_order_group, submit_order_group};
+use crate::owner::{
+    block_asset, deposit_bounty, deposit_incentive, evacuate_asset, force_cancel_order, pause_pair,
+    set_bounty_per_fill, set_pair_min_fee, sweep_native_dust, unblock_asset, unpause_pair,
+};
+use crate::querier::{query_decimals, TerraswapOracle};
+use crate::query::{
+    query_allowance_check, query_config, query_executor_stats, query_fee_token_info, query_fills,
+    query_has_open_orders, query_last_order_id, query_max_fill_within_spread, query_median_price,
+    query_optimal_fill_set, query_order, query_order_counts_by_pair, query_order_group,
+    query_order_range, query_order_stats, query_order_with_bidder_context, query_orders,
+    query_orders_by_ask_asset, query_orders_by_denom, query_orders_near_price,
+    query_orphaned_orders, query_pair_stats, query_pause_state, query_preview_execution,
+    query_preview_submit, query_prioritized_orders, query_profitable_orders, query_recent,
+    query_top_bidders, query_total_escrowed_fees, query_underwater_orders,
+};
+use crate::state::{
+    store_order, Config, LegacyConfig, LegacyOrderInfo, OrderInfo, BIDDER_ORDER_TOTALS,
+    BOUNTY_POOL, CONFIG, INCENTIVE_POOL, LAST_ORDER_GROUP_ID, LAST_ORDER_ID, LEGACY_CONFIG,
+    LEGACY_ORDERS, TOTAL_OPEN_FEES, TOTAL_OPEN_ORDERS,
+};
+
+// default max spread of the swaps executing orders, in percent
+const DEFAULT_MAX_SPREAD: u64 = 5;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    let cleanup_reward_bps: u64 = msg.cleanup_reward_bps.unwrap_or(0u64);
+    if cleanup_reward_bps > 10000u64 {
+        return Err(StdError::generic_err(
+            "cleanup_reward_bps must not exceed 10000",
+        ));
+    }
+
+    let protocol_fee_bps: u64 = msg.protocol_fee_bps.unwrap_or(0u64);
+    if protocol_fee_bps > 10000u64 {
+        return Err(StdError::generic_err(
+            "protocol_fee_bps must not exceed 10000",
+        ));
+    }
+    let treasury = msg
+        .treasury
+        .map(|treasury| deps.api.addr_validate(&treasury))
+        .transpose()?;
+    if protocol_fee_bps > 0u64 && treasury.is_none() {
+        return Err(StdError::generic_err(
+            "treasury is required with a protocol fee",
+        ));
+    }
+
+    let bidder_rebate_bps: u64 = msg.bidder_rebate_bps.unwrap_or(0u64);
+    if protocol_fee_bps + bidder_rebate_bps > 10000u64 {
+        return Err(StdError::generic_err(
+            "protocol_fee_bps and bidder_rebate_bps must not exceed 10000 together",
+        ));
+    }
+    let fee_denominated_in_ask: bool = msg.fee_denominated_in_ask.unwrap_or(false);
+    if bidder_rebate_bps > 0u64 && fee_denominated_in_ask {
+        return Err(StdError::generic_err(
+            "rebates are paid in the fee token, fees must not be in the ask asset",
+        ));
+    }
+
+    let epoch_seconds: u64 = msg.epoch_seconds.unwrap_or(0u64);
+    if msg.max_fee_per_epoch.is_some() && epoch_seconds == 0u64 {
+        return Err(StdError::generic_err(
+            "epoch_seconds is required with a max fee per epoch",
+        ));
+    }
+
     let config = Config {
+        owner: info.sender,
         fee_token: deps.api.addr_validate(msg.fee_token.as_str())?,
         min_fee_amount: msg.min_fee_amount,
         terraswap_factory: deps.api.addr_validate(msg.terraswap_factory.as_str())?,
+        fallback_factory: msg
+            .fallback_factory
+            .map(|fallback_factory| deps.api.addr_validate(&fallback_factory))
+            .transpose()?,
+        incentive_token: msg
+            .incentive_token
+            .map(|incentive_token| deps.api.addr_validate(&incentive_token))
+            .transpose()?,
+        incentive_per_fill: msg.incentive_per_fill.unwrap_or_default(),
+        retain_fills: msg.retain_fills.unwrap_or(false),
+        cleanup_reward_bps,
+        max_spread: msg
+            .max_spread
+            .unwrap_or_else(|| Decimal::percent(DEFAULT_MAX_SPREAD)),
+        fee_bump_period: msg.fee_bump_period.unwrap_or(0u64),
+        notional_asset_info: msg.notional_asset_info,
+        min_notional: msg.min_notional.unwrap_or_default(),
+        bounty_per_fill: Uint128::zero(),
+        default_order_ttl_seconds: msg.default_order_ttl_seconds,
+        allow_offer_fee: msg.allow_offer_fee.unwrap_or(false),
+        dust_threshold: msg.dust_threshold.unwrap_or_default(),
+        dynamic_fee: msg.dynamic_fee.unwrap_or(false),
+        protocol_fee_bps,
+        treasury,
+        native_refund_dust: msg.native_refund_dust.unwrap_or_default(),
+        min_order_lifetime_seconds: msg.min_order_lifetime_seconds.unwrap_or(0u64),
+        max_fee_per_epoch: msg.max_fee_per_epoch,
+        epoch_seconds,
+        fee_denominated_in_ask,
+        bidder_rebate_bps,
+        max_order_age_seconds: msg.max_order_age_seconds,
     };
 
     CONFIG.save(deps.storage, &config)?;
-    LAST_ORDER_ID.save(deps.storage, &0u64)?;
+    LAST_ORDER_GROUP_ID.save(deps.storage, &0u64)?;
+    BOUNTY_POOL.save(deps.storage, &Uint128::zero())?;
+    INCENTIVE_POOL.save(deps.storage, &Uint128::zero())?;
+    TOTAL_OPEN_FEES.save(deps.storage, &Uint128::zero())?;
+    TOTAL_OPEN_ORDERS.save(deps.storage, &0u64)?;
+
+    // seed the orders migrated from a predecessor contract
+    let mut last_order_id: u64 = 0u64;
+    for order in msg.initial_orders.unwrap_or_default().iter() {
+        if order.order_id <= last_order_id {
+            return Err(StdError::generic_err(
+                "initial order ids must be unique and ascending",
+            ));
+        }
+
+        let initial_order: OrderInfo = build_initial_order(deps.as_ref(), &config, order)?;
+        store_order(deps.storage, &initial_order)?;
+        BIDDER_ORDER_TOTALS.update(
+            deps.storage,
+            initial_order.bidder_addr.as_bytes(),
+            |total| -> StdResult<_> { Ok(total.unwrap_or(0u64) + 1u64) },
+        )?;
+        last_order_id = order.order_id;
+    }
+    LAST_ORDER_ID.save(deps.storage, &last_order_id)?;
 
     Ok(Response::default())
 }
@@ -29,18 +159,174 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
     match msg {
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, info, msg),
         ExecuteMsg::SubmitOrder {
             offer_asset,
             ask_asset,
             fee_amount,
-        } => submit_order(deps, env, info, offer_asset, ask_asset, fee_amount),
-        ExecuteMsg::CancelOrder { order_id } => cancel_order(deps, info, order_id),
-        ExecuteMsg::ExecuteOrder { order_id } => execute_order(deps, info, order_id),
+            expires_at,
+            fee_bond,
+            grid,
+            offer_fee_bps,
+            reward_ramp,
+            fill_hook,
+            max_spread_bps,
+        } => submit_order(
+            deps,
+            env,
+            info,
+            &TerraswapOracle,
+            offer_asset,
+            ask_asset,
+            fee_amount,
+            expires_at,
+            fee_bond.unwrap_or_default(),
+            grid,
+            offer_fee_bps,
+            reward_ramp,
+            fill_hook,
+            max_spread_bps,
+        ),
+        ExecuteMsg::SubmitOrders { orders } => {
+            submit_orders(deps, env, info, &TerraswapOracle, orders)
+        }
+        ExecuteMsg::SubmitSignedOrder {
+            order,
+            signature,
+            pub_key,
+        } => submit_signed_order(deps, env, info, &TerraswapOracle, order, signature, pub_key),
+        ExecuteMsg::CancelOrder {
+            order_id,
+            rebate_as_offer,
+            refund_as,
+        } => cancel_order(
+            deps,
+            env,
+            info,
+            &TerraswapOracle,
+            order_id,
+            rebate_as_offer,
+            refund_as,
+        ),
+        ExecuteMsg::ExecuteOrder {
+            order_id,
+            executor_recipient,
+        } => execute_order(
+            deps,
+            env,
+            info,
+            &TerraswapOracle,
+            order_id,
+            executor_recipient,
+            None,
+        ),
+        ExecuteMsg::ExecuteOrderWithExpectation {
+            order_id,
+            min_return,
+        } => execute_order(
+            deps,
+            env,
+            info,
+            &TerraswapOracle,
+            order_id,
+            None,
+            Some(min_return),
+        ),
+        ExecuteMsg::SubmitOrderGroup {
+            offer_asset,
+            ask_asset_info,
+            levels,
+            fee_amount,
+        } => submit_order_group(
+            deps,
+            env,
+            info,
+            &TerraswapOracle,
+            offer_asset,
+            ask_asset_info,
+            levels,
+            fee_amount,
+        ),
+        ExecuteMsg::CancelOrderGroup { group_id } => cancel_order_group(deps, env, info, group_id),
+        ExecuteMsg::RetargetOrder {
+            order_id,
+            new_pair_offer_asset,
+            new_pair_ask_asset,
+        } => retarget_order(
+            deps,
+            info,
+            &TerraswapOracle,
+            order_id,
+            new_pair_offer_asset,
+            new_pair_ask_asset,
+        ),
+        ExecuteMsg::UpdateOrder {
+            order_id,
+            reserve_price,
+        } => update_order(deps, info, order_id, reserve_price),
+        ExecuteMsg::ClaimRebates {} => claim_rebates(deps, info),
+        ExecuteMsg::Heartbeat {} => heartbeat(deps, env, info),
+        ExecuteMsg::CancelUserPairOrders { pair_addr, limit } => {
+            cancel_user_pair_orders(deps, env, info, pair_addr, limit)
+        }
+        ExecuteMsg::CleanupExpiredOrders { order_ids } => {
+            cleanup_expired_orders(deps, env, info, order_ids)
+        }
+        ExecuteMsg::BlockAsset { asset_info } => block_asset(deps, info, asset_info),
+        ExecuteMsg::UnblockAsset { asset_info } => unblock_asset(deps, info, asset_info),
+        ExecuteMsg::ForceCancelOrder { order_id } => force_cancel_order(deps, info, order_id),
+        ExecuteMsg::EvacuateAsset { asset_info, limit } => {
+            evacuate_asset(deps, info, asset_info, limit)
+        }
+        ExecuteMsg::PausePair { pair_addr } => pause_pair(deps, info, pair_addr),
+        ExecuteMsg::UnpausePair { pair_addr } => unpause_pair(deps, info, pair_addr),
+        ExecuteMsg::DepositBounty { amount } => deposit_bounty(deps, env, info, amount),
+        ExecuteMsg::DepositIncentive { amount } => deposit_incentive(deps, env, info, amount),
+        ExecuteMsg::SetBountyPerFill { bounty_per_fill } => {
+            set_bounty_per_fill(deps, info, bounty_per_fill)
+        }
+        ExecuteMsg::SetPairMinFee {
+            pair_addr,
+            min_fee_amount,
+        } => set_pair_min_fee(deps, info, pair_addr, min_fee_amount),
+        ExecuteMsg::SweepNativeDust { denom } => sweep_native_dust(deps, info, denom),
+    }
+}
+
+pub fn receive_cw20(
+    deps: DepsMut,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::TopUpFee { order_id } => top_up_fee(
+            deps,
+            &config,
+            info.sender,
+            sender,
+            order_id,
+            cw20_msg.amount,
+        ),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.id {
+        EXECUTE_ORDER_REPLY_ID => execute_order_reply(deps, env, msg.result),
+        FILL_HOOK_REPLY_ID => fill_hook_reply(msg.result),
+        id if id >= CANCEL_ORDER_REPLY_ID => {
+            cancel_order_reply(id - CANCEL_ORDER_REPLY_ID, msg.result)
+        }
+        _ => Err(StdError::generic_err("unknown reply id")),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::Order { order_id } => to_binary(&query_order(deps, order_id)?),
@@ -49,18 +335,300 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             start_after,
             limit,
             order_by,
+            min_offer_amount,
+            sort_by,
         } => to_binary(&query_orders(
             deps,
             bidder_addr,
             start_after,
             limit,
             order_by,
+            min_offer_amount,
+            sort_by,
         )?),
         QueryMsg::LastOrderId {} => to_binary(&query_last_order_id(deps)?),
+        QueryMsg::OrderStats {} => to_binary(&query_order_stats(deps)?),
+        QueryMsg::OrderGroup { group_id } => to_binary(&query_order_group(deps, group_id)?),
+        QueryMsg::Fills {
+            pair_addr,
+            start_after,
+            limit,
+        } => to_binary(&query_fills(deps, pair_addr, start_after, limit)?),
+        QueryMsg::OrdersNearPrice {
+            pair_addr,
+            offer_asset_info,
+            target_price,
+            band_bps,
+            limit,
+        } => to_binary(&query_orders_near_price(
+            deps,
+            pair_addr,
+            offer_asset_info,
+            target_price,
+            band_bps,
+            limit,
+        )?),
+        QueryMsg::ExecutorStats { executor } => to_binary(&query_executor_stats(deps, executor)?),
+        QueryMsg::UnderwaterOrders {
+            pair_addr,
+            min_shortfall_bps,
+            start_after,
+            limit,
+        } => to_binary(&query_underwater_orders(
+            deps,
+            &TerraswapOracle,
+            pair_addr,
+            min_shortfall_bps,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::PairStats { pair_addr } => to_binary(&query_pair_stats(deps, pair_addr)?),
+        QueryMsg::OrderRange { from_id, to_id } => {
+            to_binary(&query_order_range(deps, from_id, to_id)?)
+        }
+        QueryMsg::HasOpenOrders { bidder_addr } => {
+            to_binary(&query_has_open_orders(deps, bidder_addr)?)
+        }
+        QueryMsg::ProfitableOrders {
+            gas_cost_in_fee_token,
+            start_after,
+            limit,
+        } => to_binary(&query_profitable_orders(
+            deps,
+            env,
+            &TerraswapOracle,
+            gas_cost_in_fee_token,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::OptimalFillSet {
+            gas_per_fill,
+            budget,
+            start_after,
+            limit,
+        } => to_binary(&query_optimal_fill_set(
+            deps,
+            env,
+            &TerraswapOracle,
+            gas_per_fill,
+            budget,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::MaxFillWithinSpread {
+            order_id,
+            max_spread_bps,
+        } => to_binary(&query_max_fill_within_spread(
+            deps,
+            &TerraswapOracle,
+            order_id,
+            max_spread_bps,
+        )?),
+        QueryMsg::PrioritizedOrders {
+            pair_addr,
+            start_after,
+            limit,
+        } => to_binary(&query_prioritized_orders(
+            deps,
+            env,
+            pair_addr,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::OrderWithBidderContext { order_id } => {
+            to_binary(&query_order_with_bidder_context(deps, order_id)?)
+        }
+        QueryMsg::OrdersByAskAsset {
+            ask_asset,
+            start_after,
+            limit,
+        } => to_binary(&query_orders_by_ask_asset(
+            deps,
+            ask_asset,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::OrdersByDenom {
+            denom,
+            side,
+            start_after,
+            limit,
+        } => to_binary(&query_orders_by_denom(
+            deps,
+            denom,
+            side,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::MedianPrice {
+            pair_addr,
+            offer_asset_info,
+        } => to_binary(&query_median_price(deps, pair_addr, offer_asset_info)?),
+        QueryMsg::OrphanedOrders { start_after, limit } => to_binary(&query_orphaned_orders(
+            deps,
+            &TerraswapOracle,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Recent { count } => to_binary(&query_recent(deps, count)?),
+        QueryMsg::TotalEscrowedFees {} => to_binary(&query_total_escrowed_fees(deps)?),
+        QueryMsg::FeeTokenInfo {} => to_binary(&query_fee_token_info(deps)?),
+        QueryMsg::PauseState {
+            pair_addr,
+            offer_asset_info,
+            ask_asset_info,
+        } => to_binary(&query_pause_state(
+            deps,
+            pair_addr,
+            offer_asset_info,
+            ask_asset_info,
+        )?),
+        QueryMsg::OrderCountsByPair { start_after, limit } => {
+            to_binary(&query_order_counts_by_pair(deps, start_after, limit)?)
+        }
+        QueryMsg::TopBidders { start_after, limit } => {
+            to_binary(&query_top_bidders(deps, start_after, limit)?)
+        }
+        QueryMsg::PreviewSubmit {
+            sender,
+            offer_asset,
+            ask_asset_info,
+            fee_amount,
+            fee_bond,
+            rebuy_fee,
+        } => to_binary(&query_preview_submit(
+            deps,
+            sender,
+            offer_asset,
+            ask_asset_info,
+            fee_amount,
+            fee_bond,
+            rebuy_fee,
+        )?),
+        QueryMsg::PreviewExecution { order_id, executor } => to_binary(&query_preview_execution(
+            deps,
+            env,
+            &TerraswapOracle,
+            order_id,
+            executor,
+        )?),
+        QueryMsg::AllowanceCheck {
+            owner,
+            offer_asset,
+            ask_asset_info,
+            fee_amount,
+            fee_bond,
+            rebuy_fee,
+        } => to_binary(&query_allowance_check(
+            deps,
+            env,
+            owner,
+            offer_asset,
+            ask_asset_info,
+            fee_amount,
+            fee_bond,
+            rebuy_fee,
+        )?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    // only the first version has no open order count
+    if TOTAL_OPEN_ORDERS.may_load(deps.storage)?.is_some() {
+        return Ok(Response::default());
+    }
+
+    migrate_legacy_state(deps, env, msg)
+}
+
+/// Rewrites the config and orders of the first version in the current layout and
+/// indexes the orders. The new config fields take their instantiate defaults and the
+/// orders are given the migration time as created_at
+fn migrate_legacy_state(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    let owner: String = msg.owner.ok_or_else(|| {
+        StdError::generic_err("owner is required to migrate from the first version")
+    })?;
+    let legacy_config: LegacyConfig = LEGACY_CONFIG.load(deps.storage)?;
+    let config = Config {
+        owner: deps.api.addr_validate(&owner)?,
+        fee_token: legacy_config.fee_token,
+        min_fee_amount: legacy_config.min_fee_amount,
+        terraswap_factory: legacy_config.terraswap_factory,
+        fallback_factory: None,
+        incentive_token: None,
+        incentive_per_fill: Uint128::zero(),
+        retain_fills: false,
+        cleanup_reward_bps: 0u64,
+        max_spread: Decimal::percent(DEFAULT_MAX_SPREAD),
+        fee_bump_period: 0u64,
+        notional_asset_info: None,
+        min_notional: Uint128::zero(),
+        bounty_per_fill: Uint128::zero(),
+        default_order_ttl_seconds: None,
+        allow_offer_fee: false,
+        dust_threshold: Uint128::zero(),
+        dynamic_fee: false,
+        protocol_fee_bps: 0u64,
+        treasury: None,
+        native_refund_dust: Uint128::zero(),
+        min_order_lifetime_seconds: 0u64,
+        max_fee_per_epoch: None,
+        epoch_seconds: 0u64,
+        fee_denominated_in_ask: false,
+        bidder_rebate_bps: 0u64,
+        max_order_age_seconds: None,
+    };
+
+    CONFIG.save(deps.storage, &config)?;
+    LAST_ORDER_GROUP_ID.save(deps.storage, &0u64)?;
+    BOUNTY_POOL.save(deps.storage, &Uint128::zero())?;
+    INCENTIVE_POOL.save(deps.storage, &Uint128::zero())?;
+    TOTAL_OPEN_FEES.save(deps.storage, &Uint128::zero())?;
+    TOTAL_OPEN_ORDERS.save(deps.storage, &0u64)?;
+
+    let legacy_orders: Vec<LegacyOrderInfo> = LEGACY_ORDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, order) = item?;
+            Ok(order)
+        })
+        .collect::<StdResult<Vec<LegacyOrderInfo>>>()?;
+    for legacy_order in legacy_orders.into_iter() {
+        let mut order = OrderInfo {
+            order_id: legacy_order.order_id,
+            bidder_addr: legacy_order.bidder_addr,
+            factory_addr: config.terraswap_factory.clone(),
+            pair_addr: legacy_order.pair_addr,
+            offer_decimals: query_decimals(&deps.querier, &legacy_order.offer_asset.info)?,
+            ask_decimals: query_decimals(&deps.querier, &legacy_order.ask_asset.info)?,
+            offer_asset: legacy_order.offer_asset,
+            ask_asset: legacy_order.ask_asset,
+            fee_amount: legacy_order.fee_amount,
+            fee_bond: Uint128::zero(),
+            created_at: env.block.time.seconds(),
+            expires_at: None,
+            group_id: None,
+            order_hash: String::new(),
+            grid: None,
+            offer_fee: Uint128::zero(),
+            ramp_seconds: None,
+            fill_hook: None,
+            reserve_price: None,
+            max_spread: None,
+        };
+        order.order_hash = order.compute_hash();
+
+        store_order(deps.storage, &order)?;
+        BIDDER_ORDER_TOTALS.update(
+            deps.storage,
+            order.bidder_addr.as_bytes(),
+            |total| -> StdResult<_> { Ok(total.unwrap_or(0u64) + 1u64) },
+        )?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("owner", config.owner.to_string()),
+    ]))
 }
@@ -1,5 +1,11 @@
 pub mod contract;
 pub mod msg;
 mod order;
+mod order_group;
+mod owner;
+mod querier;
 mod query;
 pub mod state;
+
+#[cfg(test)]
+mod testing;
@@ -1,30 +1,288 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Uint128;
-use terraswap::asset::Asset;
+use cosmwasm_std::{Binary, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
+
+use crate::state::OrderInfo;
+use terraswap::asset::{Asset, AssetInfo};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub fee_token: String,
     pub min_fee_amount: Uint128,
     pub terraswap_factory: String,
+    /// Factory to resolve pairs from when terraswap_factory has none, e.g. during a DEX migration
+    pub fallback_factory: Option<String>,
+    /// Share of the order fee paid to whoever cleans up an expired order, 0 by default
+    pub cleanup_reward_bps: Option<u64>,
+    /// Max spread accepted by the pair when an order is executed, 5% by default
+    pub max_spread: Option<Decimal>,
+    /// Seconds over which the fee bond of an order is released to the executor,
+    /// 0 by default to release it at once
+    pub fee_bump_period: Option<u64>,
+    /// Reference asset in which the min notional of an offer is measured
+    pub notional_asset_info: Option<AssetInfo>,
+    pub min_notional: Option<Uint128>,
+    /// Lifetime given to orders submitted without expires_at
+    pub default_order_ttl_seconds: Option<u64>,
+    /// Allows orders to pay their fee as a share of the offer asset
+    pub allow_offer_fee: Option<bool>,
+    /// Payouts below it are folded into the larger recipient
+    pub dust_threshold: Option<Uint128>,
+    /// Orders migrated from a predecessor contract, with unique ascending ids.
+    /// Validated like submitted orders except for their pair, which is trusted, and rehashed.
+    /// The escrowed assets must be transferred to the contract separately
+    pub initial_orders: Option<Vec<OrderInfo>>,
+    /// Raises the min fee of orders which are large relative to the pool
+    pub dynamic_fee: Option<bool>,
+    /// Share of the executor fee sent to the treasury, requires treasury when non-zero
+    pub protocol_fee_bps: Option<u64>,
+    pub treasury: Option<String>,
+    /// Native amount withheld from each native offer refund and accrued to the protocol,
+    /// leaves headroom for send taxes on chains charging them. Defaults to 0
+    pub native_refund_dust: Option<Uint128>,
+    /// Seconds an order must stay open before its bidder can cancel it, unless expired
+    pub min_order_lifetime_seconds: Option<u64>,
+    /// Max order fee an executor can earn per epoch, requires epoch_seconds
+    pub max_fee_per_epoch: Option<Uint128>,
+    pub epoch_seconds: Option<u64>,
+    /// Fees (and min_fee_amount) are denominated in the ask asset of each order
+    /// instead of the fee token
+    pub fee_denominated_in_ask: Option<bool>,
+    /// Share of the executor fee credited back to the bidder, claimed with ClaimRebates.
+    /// Not available with fee_denominated_in_ask
+    pub bidder_rebate_bps: Option<u64>,
+    /// Orders older than this can no longer be executed, whatever their expires_at
+    pub max_order_age_seconds: Option<u64>,
+    /// Cw20 token paid to executors on top of the fee, from the pool funded with DepositIncentive
+    pub incentive_token: Option<String>,
+    pub incentive_per_fill: Option<Uint128>,
+    /// Keep filled orders, with their realized return and executor, for the Fills query
+    pub retain_fills: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    Receive(Cw20ReceiveMsg),
     /// User submits a new order
     /// Before, the user should increase allowance for the offer_asset (or send the native token) and the fee
     SubmitOrder {
         offer_asset: Asset,
         ask_asset: Asset,
         fee_amount: Uint128,
+        /// Unix time in seconds after which the order can no longer be executed
+        expires_at: Option<u64>,
+        /// Extra fee released to the executor linearly as the order ages,
+        /// the unreleased part is refunded on execution
+        fee_bond: Option<Uint128>,
+        /// Once filled, the proceeds are resubmitted as the opposite order
+        grid: Option<OrderGrid>,
+        /// Share of the offer asset paid to the executor instead of a fee token fee,
+        /// the offer_asset amount includes it. Requires allow_offer_fee
+        offer_fee_bps: Option<u64>,
+        /// Replaces fee_amount and fee_bond, see RewardRamp
+        reward_ramp: Option<RewardRamp>,
+        /// Message sent to a contract once the order is filled
+        fill_hook: Option<FillHook>,
+        /// Max spread of the swap filling the order in basis points, config max_spread by default
+        max_spread_bps: Option<u16>,
+    },
+    /// User submits up to 10 orders at once, the native offers sent and the cw20 offers
+    /// and fees approved must cover all of them
+    SubmitOrders {
+        orders: Vec<SubmitOrderItem>,
+    },
+    /// Relayer submits an order signed off-chain by its bidder. The signature is a secp256k1
+    /// signature over the sha256 of the JSON SignedOrderPayload, pub_key is the compressed
+    /// public key of the bidder. The offer and fee are pulled from the bidder with TransferFrom
+    SubmitSignedOrder {
+        order: SignedOrder,
+        signature: Binary,
+        pub_key: Binary,
     },
     /// User operation to canel an existing order
-    CancelOrder { order_id: u64 },
+    /// With rebate_as_offer, the refunded fee is swapped into the offer asset if a pair exists
+    CancelOrder {
+        order_id: u64,
+        rebate_as_offer: Option<bool>,
+        /// Swaps the refunded offer into this asset if a pair exists
+        refund_as: Option<AssetInfo>,
+    },
     /// Executor operation to execute an existing order
-    ExecuteOrder { order_id: u64 },
+    ExecuteOrder {
+        order_id: u64,
+        /// Receives the fee and excess instead of the executor
+        executor_recipient: Option<String>,
+    },
+    /// Executor operation to execute an order without simulating the swap on-chain.
+    /// The swap must return at least min_return, and the ask amount of the order
+    ExecuteOrderWithExpectation {
+        order_id: u64,
+        min_return: Uint128,
+    },
+    /// User submits a group of orders sharing a single escrow of the offer_asset
+    /// Each level is an order drawing its offer amount from the shared escrow when filled
+    /// fee_amount is paid per level
+    SubmitOrderGroup {
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        levels: Vec<OrderGroupLevel>,
+        fee_amount: Uint128,
+    },
+    /// User operation to cancel an order group and all its remaining orders
+    CancelOrderGroup {
+        group_id: u64,
+    },
+    /// User operation to point an order at the pair the factory currently
+    /// resolves for its assets
+    RetargetOrder {
+        order_id: u64,
+        new_pair_offer_asset: AssetInfo,
+        new_pair_ask_asset: AssetInfo,
+    },
+    /// Bidder operation to set the reserve price of an order, the min ask per unit of offer.
+    /// The ask amount is raised to meet it, and once set it can be raised but never lowered
+    UpdateOrder {
+        order_id: u64,
+        reserve_price: Decimal,
+    },
+    /// Bidder operation to withdraw the fee rebates accrued over their fills
+    ClaimRebates {},
+    /// Executor operation to signal liveness, records the block time for the sender
+    Heartbeat {},
+    /// User operation to cancel up to limit of their orders on a pair
+    CancelUserPairOrders {
+        pair_addr: String,
+        limit: Option<u32>,
+    },
+    /// Anyone can remove up to 30 expired orders, the bidders are refunded and
+    /// the caller receives the cleanup rewards out of the order fees in one transfer.
+    /// Ids of live or missing orders are skipped
+    CleanupExpiredOrders {
+        order_ids: Vec<u64>,
+    },
+    /// Owner operation to reject new orders offering or asking the asset
+    BlockAsset {
+        asset_info: AssetInfo,
+    },
+    /// Owner operation to accept the asset again
+    UnblockAsset {
+        asset_info: AssetInfo,
+    },
+    /// Owner operation to cancel an order regardless of min_order_lifetime_seconds,
+    /// the offer and fees are refunded to the bidder
+    ForceCancelOrder {
+        order_id: u64,
+    },
+    /// Owner operation to cancel up to limit of the orders offering or asking a blocked asset,
+    /// the offer and fees are refunded to the bidders
+    EvacuateAsset {
+        asset_info: AssetInfo,
+        limit: Option<u32>,
+    },
+    /// Owner operation to halt submission and execution of orders on the pair
+    PausePair {
+        pair_addr: String,
+    },
+    /// Owner operation to resume the pair
+    UnpausePair {
+        pair_addr: String,
+    },
+    /// Owner operation to fund the executor bounty pool with fee tokens
+    DepositBounty {
+        amount: Uint128,
+    },
+    /// Owner operation to fund the executor incentive pool with incentive tokens
+    DepositIncentive {
+        amount: Uint128,
+    },
+    /// Owner operation to set the bounty paid per fill
+    SetBountyPerFill {
+        bounty_per_fill: Uint128,
+    },
+    /// Owner operation to override min_fee_amount on the pair, None to reset it
+    SetPairMinFee {
+        pair_addr: String,
+        min_fee_amount: Option<Uint128>,
+    },
+    /// Owner operation to send the native refund dust accrued in denom to the treasury,
+    /// or to the owner when no treasury is set
+    SweepNativeDust {
+        denom: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    /// Bidder operation to add the fee tokens sent to the fee of the order
+    TopUpFee { order_id: u64 },
+}
+
+/// Order of a SubmitOrders batch, same fields as SubmitOrder
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubmitOrderItem {
+    pub offer_asset: Asset,
+    pub ask_asset: Asset,
+    pub fee_amount: Uint128,
+    pub expires_at: Option<u64>,
+    pub fee_bond: Option<Uint128>,
+    pub grid: Option<OrderGrid>,
+    pub offer_fee_bps: Option<u64>,
+    pub reward_ramp: Option<RewardRamp>,
+    pub fill_hook: Option<FillHook>,
+    pub max_spread_bps: Option<u16>,
+}
+
+/// Order signed by the bidder, nonce tells apart otherwise identical orders
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedOrder {
+    pub offer_asset: Asset,
+    pub ask_asset: Asset,
+    pub fee_amount: Uint128,
+    pub expires_at: Option<u64>,
+    pub nonce: u64,
+}
+
+/// Message signed for a SubmitSignedOrder, bound to the chain and this contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedOrderPayload {
+    pub chain_id: String,
+    pub contract_addr: String,
+    pub order: SignedOrder,
+}
+
+/// Executed on contract with msg after the order is settled, a failing hook doesn't
+/// revert the fill
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FillHook {
+    pub contract: String,
+    pub msg: Binary,
+}
+
+/// Executor fee rising linearly from start_fee to end_fee over ramp_seconds since submission.
+/// end_fee is escrowed upfront and the part not yet released is refunded on fill
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardRamp {
+    pub start_fee: Uint128,
+    pub end_fee: Uint128,
+    pub ramp_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderGrid {
+    /// Offer asset of the order asked per unit of proceeds by the mirrored order
+    pub rebuy_price: Decimal,
+    /// Fee of the mirrored order, escrowed on submit
+    pub rebuy_fee: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderGroupLevel {
+    pub offer_amount: Uint128,
+    pub ask_amount: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -39,30 +297,254 @@ pub enum QueryMsg {
         start_after: Option<u64>,
         limit: Option<u32>,
         order_by: Option<OrderBy>,
+        /// Only returns the orders of the page offering at least this amount, so a page
+        /// may hold fewer orders than limit while next_start_after is set
+        min_offer_amount: Option<Uint128>,
+        /// Sorts the page read in order_by, pagination is still done by order id
+        sort_by: Option<OrderSort>,
     },
     LastOrderId {},
+    /// Last order id along with the number of orders still open
+    OrderStats {},
+    OrderGroup {
+        group_id: u64,
+    },
+    /// Filled orders of the pair, most recent first. Only recorded with retain_fills
+    Fills {
+        pair_addr: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Orders of a pair offering offer_asset_info whose implied price (ask per offer,
+    /// in whole tokens) is within target_price +/- band_bps, ascending by price
+    OrdersNearPrice {
+        pair_addr: String,
+        offer_asset_info: AssetInfo,
+        target_price: Decimal,
+        band_bps: u64,
+        limit: Option<u32>,
+    },
+    /// Orders of a pair whose simulated return falls short of their ask amount by more than
+    /// min_shortfall_bps of it, i.e. unlikely to fill soon. Side by side, ascending by implied price.
+    /// Only the limit orders after start_after are simulated, start_after is the next_start_after
+    /// of the previous page and stays valid once its order is closed
+    UnderwaterOrders {
+        pair_addr: String,
+        min_shortfall_bps: u64,
+        start_after: Option<PriceCursor>,
+        limit: Option<u32>,
+    },
+    ExecutorStats {
+        executor: String,
+    },
+    /// Number of fills of the pair and the average fee paid per fill. With
+    /// fee_denominated_in_ask the fees of both sides are averaged as is
+    PairStats {
+        pair_addr: String,
+    },
+    /// Existing orders with from_id <= order_id <= to_id, ascending
+    OrderRange {
+        from_id: u64,
+        to_id: u64,
+    },
+    HasOpenOrders {
+        bidder_addr: String,
+    },
+    /// Fillable orders whose executor reward, fee plus excess valued in the fee token,
+    /// covers gas_cost_in_fee_token. Only the limit orders after start_after are simulated,
+    /// next_start_after continues the scan whether or not they were profitable
+    ProfitableOrders {
+        gas_cost_in_fee_token: Uint128,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Ids of the orders a keeper should fill with budget gas, each fill costing gas_per_fill.
+    /// The limit orders after start_after are simulated and the best rewards taken
+    /// greedily, ties broken by ascending order_id
+    OptimalFillSet {
+        gas_per_fill: u64,
+        budget: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Largest offer amount of the order whose swap keeps the spread within max_spread_bps,
+    /// found by binary search over simulations. Orders are filled whole, so a fill_amount
+    /// below the offer amount means the order can't be executed within that spread yet
+    MaxFillWithinSpread {
+        order_id: u64,
+        max_spread_bps: u64,
+    },
+    /// Orders of the pair by descending priority_score, see OrderInfo::priority_score.
+    /// Each page ranks the next 100 orders of the pair after start_after, side by side by
+    /// implied price. The full set is ranked by following next_start_after and merging the pages
+    PrioritizedOrders {
+        pair_addr: String,
+        start_after: Option<PriceCursor>,
+        limit: Option<u32>,
+    },
+    /// Order with the open order count of its bidder and the bidder's escrow in its offer asset
+    OrderWithBidderContext {
+        order_id: u64,
+    },
+    /// Orders asking the asset, ascending by order id
+    OrdersByAskAsset {
+        ask_asset: AssetInfo,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Orders offering or asking the native denom, ascending by order id.
+    /// The full denom is matched, e.g. ibc/<hash>
+    OrdersByDenom {
+        denom: String,
+        side: OrderSide,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Median implied price of the orders of the pair offering the asset.
+    /// Only the MAX_LIMIT lowest priced orders are considered
+    MedianPrice {
+        pair_addr: String,
+        offer_asset_info: AssetInfo,
+    },
+    /// Orders among a page of limit orders whose pair no longer resolves in the factory.
+    /// next_start_after continues the scan
+    OrphanedOrders {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Config and the count most recent orders, descending
+    Recent {
+        count: u32,
+    },
+    /// Sum of the fees, fee bonds and rebuy fees escrowed for the open orders, per ask asset
+    /// when fees are denominated in the ask asset
+    TotalEscrowedFees {},
+    /// Symbol and decimals of the fee token, read from its cw20 token info
+    FeeTokenInfo {},
+    /// Pause flags applying to the pair and the assets provided
+    PauseState {
+        pair_addr: Option<String>,
+        offer_asset_info: Option<AssetInfo>,
+        ask_asset_info: Option<AssetInfo>,
+    },
+    /// Number of open orders of every pair, ascending by pair_addr
+    OrderCountsByPair {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Number of orders ever submitted by every bidder, ascending by bidder_addr.
+    /// Not sorted by count, clients rank the pages themselves
+    TopBidders {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Transfers a SubmitOrder from sender would require. The fee, fee bond and rebuy fee
+    /// are transferred in the fee asset of ask_asset_info
+    PreviewSubmit {
+        sender: String,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        fee_amount: Uint128,
+        fee_bond: Option<Uint128>,
+        rebuy_fee: Option<Uint128>,
+    },
+    /// Payout of executing the order now by executor, from the current pair simulation
+    PreviewExecution {
+        order_id: u64,
+        executor: String,
+    },
+    /// Whether the allowance of owner to this contract covers a cw20 offer, plus the fee, fee
+    /// bond and rebuy fee when the offer is the fee asset of ask_asset_info. Native offers are
+    /// always sufficient, the allowance for a separate fee is checked with the fee asset as
+    /// offer_asset and zero fees
+    AllowanceCheck {
+        owner: String,
+        offer_asset: Asset,
+        ask_asset_info: AssetInfo,
+        fee_amount: Uint128,
+        fee_bond: Option<Uint128>,
+        rebuy_fee: Option<Uint128>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
+    pub owner: String,
     pub fee_token: String,
     pub min_fee_amount: Uint128,
     pub terraswap_factory: String,
+    pub cleanup_reward_bps: u64,
+    pub max_spread: Decimal,
+    pub fee_bump_period: u64,
+    pub notional_asset_info: Option<AssetInfo>,
+    pub min_notional: Uint128,
+    pub bounty_per_fill: Uint128,
+    pub default_order_ttl_seconds: Option<u64>,
+    pub allow_offer_fee: bool,
+    pub dust_threshold: Uint128,
+    pub dynamic_fee: bool,
+    pub protocol_fee_bps: u64,
+    pub treasury: Option<String>,
+    pub native_refund_dust: Uint128,
+    pub min_order_lifetime_seconds: u64,
+    pub max_fee_per_epoch: Option<Uint128>,
+    pub epoch_seconds: u64,
+    pub fee_denominated_in_ask: bool,
+    pub bidder_rebate_bps: u64,
+    pub max_order_age_seconds: Option<u64>,
+    pub fallback_factory: Option<String>,
+    pub incentive_token: Option<String>,
+    pub incentive_per_fill: Uint128,
+    pub retain_fills: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct OrderResponse {
     pub order_id: u64,
     pub bidder_addr: String,
+    pub factory_addr: String,
     pub pair_addr: String,
     pub offer_asset: Asset,
     pub ask_asset: Asset,
     pub fee_amount: Uint128,
+    pub fee_bond: Uint128,
+    /// block time of submission, in seconds
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub group_id: Option<u64>,
+    /// sha256 of the order fields, see OrderInfo::compute_hash
+    pub order_hash: String,
+    pub grid: Option<OrderGrid>,
+    pub offer_fee: Uint128,
+    pub ramp_seconds: Option<u64>,
+    pub fill_hook: Option<FillHook>,
+    pub reserve_price: Option<Decimal>,
+    pub max_spread: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct OrdersResponse {
     pub orders: Vec<OrderResponse>,
+    /// start_after of the next page, None once the last page is returned
+    pub next_start_after: Option<u64>,
+    /// open orders overall, or of the bidder when filtered by bidder_addr.
+    /// Only set by the Orders query, min_offer_amount is not taken into account
+    pub total_open: Option<u64>,
+}
+
+/// Position in the price index of a pair: the offer side, the implied price and the order id
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceCursor {
+    pub offer_asset_info: AssetInfo,
+    pub price: Uint128,
+    pub order_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnderwaterOrdersResponse {
+    pub orders: Vec<OrderResponse>,
+    /// start_after of the next page, None once the last page is returned
+    pub next_start_after: Option<PriceCursor>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -70,6 +552,168 @@ pub struct LastOrderIdResponse {
     pub last_order_id: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderStatsResponse {
+    pub last_order_id: u64,
+    /// order ids are assigned sequentially, so this is last_order_id
+    pub total_created: u64,
+    pub open_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FillResponse {
+    pub order: OrderResponse,
+    pub filled_return: Uint128,
+    pub executor: String,
+    pub filled_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FillsResponse {
+    pub fills: Vec<FillResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderGroupResponse {
+    pub group_id: u64,
+    pub bidder_addr: String,
+    pub pair_addr: String,
+    pub escrow_asset: Asset,
+    pub order_ids: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PairStatsResponse {
+    pub pair_addr: String,
+    pub fill_count: u64,
+    pub average_fee: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExecutorStatsResponse {
+    pub executor: String,
+    pub fill_count: u64,
+    pub last_filled_order_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HasOpenOrdersResponse {
+    pub has_open_orders: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PrioritizedOrder {
+    pub order: OrderResponse,
+    pub priority_score: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PrioritizedOrdersResponse {
+    pub orders: Vec<PrioritizedOrder>,
+    /// start_after of the next page, None once the last orders of the pair are ranked
+    pub next_start_after: Option<PriceCursor>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderWithBidderContextResponse {
+    pub order: OrderResponse,
+    pub bidder_open_order_count: u64,
+    /// offer asset of the order escrowed over all open orders of the bidder
+    pub bidder_escrowed_offer: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MedianPriceResponse {
+    /// ask amount per offer amount, None without orders
+    pub median_price: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecentResponse {
+    pub config: ConfigResponse,
+    pub orders: Vec<OrderResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalEscrowedFeesResponse {
+    /// in the fee token
+    pub total_escrowed_fees: Uint128,
+    /// by ask asset when fees are denominated in the ask asset
+    pub ask_escrowed_fees: Vec<Asset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OptimalFillSetResponse {
+    /// descending by reward
+    pub order_ids: Vec<u64>,
+    /// executor reward of the selected orders, valued in the fee token
+    pub total_reward: Uint128,
+    /// start_after of the next orders to simulate, None once the last order is scanned
+    pub next_start_after: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MaxFillWithinSpreadResponse {
+    pub fill_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeTokenInfoResponse {
+    pub fee_token: String,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseStateResponse {
+    pub pair_paused: bool,
+    pub blocked_offer: bool,
+    pub blocked_ask: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderCountsByPairResponse {
+    /// (pair_addr, count)
+    pub order_counts: Vec<(String, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TopBiddersResponse {
+    /// (bidder_addr, total orders submitted)
+    pub bidders: Vec<(String, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewSubmitResponse {
+    pub transfers: Vec<TransferPreview>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PreviewExecutionResponse {
+    pub bidder_receives: Asset,
+    /// fee paid to the executor, net of the protocol fee and the bidder rebate
+    pub executor_fee: Asset,
+    pub executor_excess: Asset,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowanceCheckResponse {
+    pub sufficient: bool,
+    /// unexpired allowance of the owner to this contract
+    pub current: Uint128,
+    pub required: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferPreview {
+    pub from: String,
+    pub asset: Asset,
+    /// cw20 transfers are pulled with TransferFrom and need an allowance to this contract,
+    /// native transfers must be sent along with the message
+    pub allowance_required: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum OrderBy {
@@ -78,4 +722,27 @@ pub enum OrderBy {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+#[serde(rename_all = "snake_case")]
+pub enum OrderSort {
+    /// Keeps the order_by order
+    Id,
+    /// Lowest implied price first
+    Price,
+    /// Highest fee first
+    Fee,
+    /// Oldest order first
+    Age,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Owner of the contract, required when migrating from the first version which has none
+    pub owner: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSide {
+    Offer,
+    Ask,
+}
@@ -1,126 +1,935 @@
-use crate::state::{remove_order, store_new_order, Config, OrderInfo, CONFIG, ORDERS};
+use std::collections::BTreeMap;
+
+use crate::msg::{
+    FillHook, OrderGrid, RewardRamp, SignedOrder, SignedOrderPayload, SubmitOrderItem,
+};
+use crate::order_group::release_group_order;
+use crate::querier::{query_decimals, query_notional, query_pool_reserve, PairOracle};
+use crate::state::{
+    assert_assets_not_blocked, assert_executable, assert_min_fee, assert_min_lifetime,
+    assert_pair_assets, assert_pair_not_paused, checked_mul_decimal, increase_open_fees,
+    read_orders_by_user_pair, remove_order, store_new_order, store_order, Config, FillInfo,
+    FillPayout, OrderInfo, PendingExecution, ACCRUED_NATIVE_DUST, BOUNTY_POOL, CONFIG,
+    FEES_BY_PAIR, FEE_EARNED_EPOCH, FILLS, FILLS_BY_EXECUTOR, FILLS_BY_PAIR, INCENTIVE_POOL,
+    LAST_FILL_BY_EXECUTOR, LAST_HEARTBEAT, ORDERS, PENDING_EXECUTION, REBATES,
+    SIGNED_ORDER_DIGESTS,
+};
 use cosmwasm_std::{
-    attr, to_binary, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
-    Uint128, WasmMsg,
+    attr, to_binary, to_vec, Addr, Attribute, Binary, CanonicalAddr, Coin, ContractResult,
+    CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Response, StdError,
+    StdResult, Storage, SubMsg, SubMsgExecutionResponse, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
+use ripemd160::Ripemd160;
+use sha2::{Digest, Sha256};
 use terraswap::asset::{Asset, AssetInfo, PairInfo};
 use terraswap::pair::{
     Cw20HookMsg as PairCw20HookMsg, ExecuteMsg as PairExecuteMsg, SimulationResponse,
 };
-use terraswap::querier::{query_pair_info, simulate};
 
+// reply id of the swap executing an order
+pub const EXECUTE_ORDER_REPLY_ID: u64 = 1;
+// reply ids of the refunds of a canceled order, the order id is added to it so the
+// reply knows which order it belongs to without keeping state
+pub const CANCEL_ORDER_REPLY_ID: u64 = 1u64 << 63;
+// reply id of the fill hook of a settled order
+pub const FILL_HOOK_REPLY_ID: u64 = 3;
+// max number of orders submitted at once
+const MAX_BATCH_ORDERS: usize = 10;
+// max number of expired orders cleaned up at once
+const MAX_CLEANUP_ORDERS: usize = 30;
+
+#[allow(clippy::too_many_arguments)]
 pub fn submit_order(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    oracle: &dyn PairOracle,
     offer_asset: Asset,
     ask_asset: Asset,
     fee_amount: Uint128,
+    expires_at: Option<u64>,
+    fee_bond: Uint128,
+    grid: Option<OrderGrid>,
+    offer_fee_bps: Option<u64>,
+    reward_ramp: Option<RewardRamp>,
+    fill_hook: Option<FillHook>,
+    max_spread_bps: Option<u16>,
 ) -> StdResult<Response> {
     let config: Config = CONFIG.load(deps.storage)?;
+    let mut new_order: OrderInfo = build_order(
+        deps.as_ref(),
+        &env,
+        oracle,
+        &config,
+        &info.sender,
+        SubmitOrderItem {
+            offer_asset: offer_asset.clone(),
+            ask_asset: ask_asset.clone(),
+            fee_amount,
+            expires_at,
+            fee_bond: Some(fee_bond),
+            grid,
+            offer_fee_bps,
+            reward_ramp,
+            fill_hook,
+            max_spread_bps,
+        },
+    )?;
+    let messages: Vec<CosmosMsg> = escrow_msgs(
+        &env,
+        &info,
+        &info.sender,
+        &config,
+        &[new_order.clone()],
+        None,
+    )?;
+    store_new_order(deps.storage, &mut new_order)?;
 
-    if fee_amount < config.min_fee_amount {
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "submit_order"),
+        attr("order_id", new_order.order_id.to_string()),
+        attr("bidder_addr", info.sender.to_string()),
+        attr("offer_asset", offer_asset.to_string()),
+        attr("ask_asset", ask_asset.to_string()),
+        attr("order_hash", new_order.order_hash),
+    ]))
+}
+
+/// Submits up to MAX_BATCH_ORDERS orders at once, each validated as a single order
+pub fn submit_orders(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    oracle: &dyn PairOracle,
+    orders: Vec<SubmitOrderItem>,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if orders.is_empty() || orders.len() > MAX_BATCH_ORDERS {
         return Err(StdError::generic_err(format!(
-            "fee should be greater than {}",
-            config.min_fee_amount
+            "must submit between 1 and {} orders",
+            MAX_BATCH_ORDERS
         )));
     }
 
-    // check if the pair exists
-    let pair_info: PairInfo = query_pair_info(
+    let mut new_orders: Vec<OrderInfo> = vec![];
+    for item in orders.into_iter() {
+        new_orders.push(build_order(
+            deps.as_ref(),
+            &env,
+            oracle,
+            &config,
+            &info.sender,
+            item,
+        )?);
+    }
+
+    let messages: Vec<CosmosMsg> =
+        escrow_msgs(&env, &info, &info.sender, &config, &new_orders, None)?;
+    for new_order in new_orders.iter_mut() {
+        store_new_order(deps.storage, new_order)?;
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "submit_orders"),
+        attr("bidder_addr", info.sender.to_string()),
+        attr(
+            "order_ids",
+            new_orders
+                .iter()
+                .map(|order| order.order_id.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+    ]))
+}
+
+pub fn submit_signed_order(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    oracle: &dyn PairOracle,
+    order: SignedOrder,
+    signature: Binary,
+    pub_key: Binary,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    // the relayer can't send native offers or fees on behalf of the bidder
+    if order.offer_asset.is_native_token() {
+        return Err(StdError::generic_err(
+            "signed orders must offer a cw20 token",
+        ));
+    }
+    if let AssetInfo::NativeToken { .. } = config.fee_asset_info(&order.ask_asset.info) {
+        return Err(StdError::generic_err(
+            "signed orders must pay their fee in a cw20 token",
+        ));
+    }
+
+    let payload = SignedOrderPayload {
+        chain_id: env.block.chain_id.clone(),
+        contract_addr: env.contract.address.to_string(),
+        order: order.clone(),
+    };
+    let digest: Vec<u8> = Sha256::digest(&to_vec(&payload)?).to_vec();
+    if SIGNED_ORDER_DIGESTS.has(deps.storage, &digest) {
+        return Err(StdError::generic_err("signed order already submitted"));
+    }
+
+    let verified: bool = deps
+        .api
+        .secp256k1_verify(&digest, &signature, &pub_key)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    if !verified {
+        return Err(StdError::generic_err("invalid signature"));
+    }
+
+    // the bidder is the account of the public key: ripemd160(sha256(compressed public key))
+    if pub_key.len() != 33 {
+        return Err(StdError::generic_err("public key must be compressed"));
+    }
+    let bidder_addr: Addr = deps.api.addr_humanize(&CanonicalAddr::from(
+        Ripemd160::digest(&Sha256::digest(pub_key.as_slice())).to_vec(),
+    ))?;
+
+    let mut new_order: OrderInfo = build_order(
+        deps.as_ref(),
+        &env,
+        oracle,
+        &config,
+        &bidder_addr,
+        SubmitOrderItem {
+            offer_asset: order.offer_asset.clone(),
+            ask_asset: order.ask_asset.clone(),
+            fee_amount: order.fee_amount,
+            expires_at: order.expires_at,
+            fee_bond: None,
+            grid: None,
+            offer_fee_bps: None,
+            reward_ramp: None,
+            fill_hook: None,
+            max_spread_bps: None,
+        },
+    )?;
+    let messages: Vec<CosmosMsg> = escrow_msgs(
+        &env,
+        &info,
+        &bidder_addr,
+        &config,
+        &[new_order.clone()],
+        None,
+    )?;
+    SIGNED_ORDER_DIGESTS.save(deps.storage, &digest, &true)?;
+    store_new_order(deps.storage, &mut new_order)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "submit_signed_order"),
+        attr("order_id", new_order.order_id.to_string()),
+        attr("bidder_addr", bidder_addr.to_string()),
+        attr("relayer", info.sender.to_string()),
+        attr("offer_asset", order.offer_asset.to_string()),
+        attr("ask_asset", order.ask_asset.to_string()),
+        attr("order_hash", new_order.order_hash),
+    ]))
+}
+
+/// Validates a submitted order and builds it with a provisional id and hash
+pub fn build_order(
+    deps: Deps,
+    env: &Env,
+    oracle: &dyn PairOracle,
+    config: &Config,
+    bidder_addr: &Addr,
+    item: SubmitOrderItem,
+) -> StdResult<OrderInfo> {
+    let SubmitOrderItem {
+        offer_asset,
+        ask_asset,
+        fee_amount,
+        expires_at,
+        fee_bond,
+        grid,
+        offer_fee_bps,
+        reward_ramp,
+        fill_hook,
+        max_spread_bps,
+    } = item;
+
+    if let Some(fill_hook) = fill_hook.as_ref() {
+        deps.api.addr_validate(&fill_hook.contract)?;
+    }
+    let mut fee_amount: Uint128 = fee_amount;
+    let mut fee_bond: Uint128 = fee_bond.unwrap_or_default();
+
+    // a reward ramp is a fee bond released over its own period
+    let ramp_seconds: Option<u64> = if let Some(reward_ramp) = reward_ramp {
+        if !fee_bond.is_zero() {
+            return Err(StdError::generic_err(
+                "fee_bond cannot be combined with a reward ramp",
+            ));
+        }
+        if reward_ramp.ramp_seconds == 0 {
+            return Err(StdError::generic_err(
+                "ramp_seconds must be greater than zero",
+            ));
+        }
+
+        fee_amount = reward_ramp.start_fee;
+        fee_bond = reward_ramp
+            .end_fee
+            .checked_sub(reward_ramp.start_fee)
+            .map_err(|_| StdError::generic_err("end_fee must not be less than start_fee"))?;
+        Some(reward_ramp.ramp_seconds)
+    } else {
+        None
+    };
+
+    let max_spread: Option<Decimal> = match max_spread_bps {
+        Some(max_spread_bps) if max_spread_bps > 10000u16 => {
+            return Err(StdError::generic_err(
+                "max_spread_bps must not exceed 10000",
+            ));
+        }
+        Some(max_spread_bps) => Some(Decimal::from_ratio(u64::from(max_spread_bps), 10000u64)),
+        None => None,
+    };
+
+    if offer_asset.amount.is_zero() {
+        return Err(StdError::generic_err(
+            "offer amount must be greater than zero",
+        ));
+    }
+
+    if matches!(expires_at, Some(expires_at) if expires_at <= env.block.time.seconds()) {
+        return Err(StdError::generic_err("expires_at must be in the future"));
+    }
+
+    // fall back to the default ttl when no expiry is given
+    let expires_at: Option<u64> = expires_at.or_else(|| {
+        config
+            .default_order_ttl_seconds
+            .map(|ttl| env.block.time.seconds() + ttl)
+    });
+
+    assert_assets_not_blocked(
+        deps.storage,
+        &[offer_asset.info.clone(), ask_asset.info.clone()],
+    )?;
+
+    // check if the offer is worth the min notional
+    if let Some(notional_asset_info) = config.notional_asset_info.clone() {
+        let notional: Uint128 = query_notional(
+            &deps.querier,
+            oracle,
+            config.terraswap_factory.clone(),
+            &offer_asset,
+            notional_asset_info,
+        )?;
+        if notional < config.min_notional {
+            return Err(StdError::generic_err(format!(
+                "offer notional should be greater than {}",
+                config.min_notional
+            )));
+        }
+    }
+
+    // check if the pair exists, in the fallback factory if not in the primary one
+    let (factory_addr, pair_info): (Addr, PairInfo) = oracle.query_pair_info_with_fallback(
         &deps.querier,
-        config.terraswap_factory,
+        config.terraswap_factory.clone(),
+        config.fallback_factory.clone(),
+        &[offer_asset.info.clone(), ask_asset.info.clone()],
+    )?;
+    assert_pair_assets(
+        &pair_info,
         &[offer_asset.info.clone(), ask_asset.info.clone()],
-    )
-    .map_err(|_| StdError::generic_err("there is no terraswap pair for the 2 assets provided"))?;
+    )?;
+    let pair_addr = deps.api.addr_validate(pair_info.contract_addr.as_str())?;
+    assert_pair_not_paused(deps.storage, &pair_addr)?;
+    // the fee is taken out of the offer, the rest is swapped
+    let offer_fee: Uint128 = if let Some(offer_fee_bps) = offer_fee_bps {
+        if !config.allow_offer_fee {
+            return Err(StdError::generic_err("offer fees are not allowed"));
+        }
+        if offer_fee_bps == 0 || offer_fee_bps >= 10000u64 {
+            return Err(StdError::generic_err(
+                "offer_fee_bps must be between 0 and 10000",
+            ));
+        }
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+        offer_asset.amount.multiply_ratio(offer_fee_bps, 10000u64)
+    } else {
+        // with dynamic fees, an order of 1% of the pool reserve doubles the global min fee
+        let dynamic_min_fee: Uint128 = if config.dynamic_fee {
+            let offer_reserve: Uint128 =
+                query_pool_reserve(&deps.querier, pair_addr.clone(), &offer_asset.info)?;
+            if offer_reserve.is_zero() {
+                return Err(StdError::generic_err("the pair has no liquidity"));
+            }
 
-    match offer_asset.info.clone() {
-        AssetInfo::NativeToken { .. } => offer_asset.assert_sent_native_token_balance(&info)?,
-        AssetInfo::Token { contract_addr } => {
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr,
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
-                    owner: info.sender.to_string(),
-                    recipient: env.contract.address.to_string(),
-                    amount: offer_asset.amount,
-                })?,
-            }));
+            config.min_fee_amount.multiply_ratio(
+                offer_asset.amount.checked_mul(Uint128::from(100u128))?,
+                offer_reserve,
+            )
+        } else {
+            Uint128::zero()
+        };
+        assert_min_fee(
+            deps.storage,
+            config,
+            &pair_addr,
+            fee_amount,
+            dynamic_min_fee,
+        )?;
+        Uint128::zero()
+    };
+
+    // the mirrored order must be a valid order on its own
+    if let Some(grid) = grid.clone() {
+        // the rebuy fee would be escrowed in the ask asset but owed in the offer asset
+        if config.fee_denominated_in_ask {
+            return Err(StdError::generic_err(
+                "grid orders are not supported with fees in the ask asset",
+            ));
         }
+        if grid.rebuy_price.is_zero() {
+            return Err(StdError::generic_err(
+                "rebuy_price must be greater than zero",
+            ));
+        }
+        assert_min_fee(
+            deps.storage,
+            config,
+            &pair_addr,
+            grid.rebuy_fee,
+            Uint128::zero(),
+        )?;
     }
 
-    // transfer fee to self
-    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: config.fee_token.to_string(),
-        funds: vec![],
-        msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
-            owner: info.sender.to_string(),
-            recipient: env.contract.address.to_string(),
-            amount: fee_amount,
-        })?,
-    }));
-
-    let mut new_order = OrderInfo {
+    Ok(OrderInfo {
         order_id: 0u64, // provisional
-        bidder_addr: deps.api.addr_validate(info.sender.as_str())?,
-        pair_addr: deps.api.addr_validate(pair_info.contract_addr.as_str())?,
-        offer_asset: offer_asset.clone(),
+        bidder_addr: bidder_addr.clone(),
+        factory_addr,
+        pair_addr,
+        offer_asset: Asset {
+            info: offer_asset.info.clone(),
+            amount: offer_asset.amount.checked_sub(offer_fee)?,
+        },
         ask_asset: ask_asset.clone(),
+        offer_decimals: query_decimals(&deps.querier, &offer_asset.info)?,
+        ask_decimals: query_decimals(&deps.querier, &ask_asset.info)?,
         fee_amount,
+        fee_bond,
+        created_at: env.block.time.seconds(),
+        expires_at,
+        group_id: None,
+        order_hash: String::new(), // provisional
+        grid,
+        offer_fee,
+        ramp_seconds,
+        fill_hook,
+        reserve_price: None,
+        max_spread,
+    })
+}
+
+/// Validates an order migrated from a predecessor contract and normalizes it the way
+/// build_order builds a submitted order. Its pair and decimals are trusted as is
+pub fn build_initial_order(deps: Deps, config: &Config, order: &OrderInfo) -> StdResult<OrderInfo> {
+    if order.group_id.is_some() {
+        return Err(StdError::generic_err(
+            "initial orders must not belong to a group",
+        ));
+    }
+    if order.offer_asset.amount.is_zero() {
+        return Err(StdError::generic_err(
+            "offer amount must be greater than zero",
+        ));
+    }
+    if let Some(fill_hook) = order.fill_hook.as_ref() {
+        deps.api.addr_validate(&fill_hook.contract)?;
+    }
+    if order.ramp_seconds == Some(0u64) {
+        return Err(StdError::generic_err(
+            "ramp_seconds must be greater than zero",
+        ));
+    }
+    if matches!(order.max_spread, Some(max_spread) if max_spread > Decimal::one()) {
+        return Err(StdError::generic_err("max_spread must not exceed 1"));
+    }
+    // the escrowed fee must stay refundable
+    order.escrowed_fee()?;
+
+    let mut initial_order = OrderInfo {
+        bidder_addr: deps.api.addr_validate(order.bidder_addr.as_str())?,
+        factory_addr: deps.api.addr_validate(order.factory_addr.as_str())?,
+        pair_addr: deps.api.addr_validate(order.pair_addr.as_str())?,
+        ..order.clone()
     };
-    store_new_order(deps.storage, &mut new_order)?;
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "submit_order"),
-        attr("order_id", new_order.order_id.to_string()),
-        attr("bidder_addr", info.sender.to_string()),
-        attr("offer_asset", offer_asset.to_string()),
-        attr("ask_asset", ask_asset.to_string()),
-    ]))
+    if initial_order.offer_fee.is_zero() {
+        assert_min_fee(
+            deps.storage,
+            config,
+            &initial_order.pair_addr,
+            initial_order.fee_amount,
+            Uint128::zero(),
+        )?;
+    } else if !config.allow_offer_fee {
+        return Err(StdError::generic_err("offer fees are not allowed"));
+    }
+
+    if let Some(grid) = initial_order.grid.clone() {
+        if config.fee_denominated_in_ask {
+            return Err(StdError::generic_err(
+                "grid orders are not supported with fees in the ask asset",
+            ));
+        }
+        if grid.rebuy_price.is_zero() {
+            return Err(StdError::generic_err(
+                "rebuy_price must be greater than zero",
+            ));
+        }
+        assert_min_fee(
+            deps.storage,
+            config,
+            &initial_order.pair_addr,
+            grid.rebuy_fee,
+            Uint128::zero(),
+        )?;
+    }
+
+    initial_order.order_hash = initial_order.compute_hash();
+    Ok(initial_order)
+}
+
+/// Asserts the native offers of the orders were sent and transfers the cw20 offers
+/// and the fees from owner, with a single TransferFrom per token.
+/// The orders of a group draw from shared_offer instead of escrowing their own offer
+pub fn escrow_msgs(
+    env: &Env,
+    info: &MessageInfo,
+    owner: &Addr,
+    config: &Config,
+    orders: &[OrderInfo],
+    shared_offer: Option<&Asset>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let escrowed_offers: Vec<Asset> = match shared_offer {
+        Some(shared_offer) => vec![shared_offer.clone()],
+        None => orders.iter().map(|order| order.escrowed_offer()).collect(),
+    };
+
+    let mut native_amounts: BTreeMap<String, Uint128> = BTreeMap::new();
+    let mut token_amounts: BTreeMap<String, Uint128> = BTreeMap::new();
+    for escrowed_offer in escrowed_offers.into_iter() {
+        let amount: &mut Uint128 = match escrowed_offer.info {
+            AssetInfo::NativeToken { denom } => native_amounts.entry(denom).or_default(),
+            AssetInfo::Token { contract_addr } => token_amounts.entry(contract_addr).or_default(),
+        };
+        *amount = amount
+            .checked_add(escrowed_offer.amount)
+            .map_err(|_| StdError::generic_err("escrowed offer amount overflows"))?;
+    }
+
+    for order in orders.iter() {
+        // fee, fee bond and rebuy fee, added to the offer when paid in the same asset
+        let fee_amount: &mut Uint128 = match config.fee_asset_info(&order.ask_asset.info) {
+            AssetInfo::NativeToken { denom } => native_amounts.entry(denom).or_default(),
+            AssetInfo::Token { contract_addr } => token_amounts.entry(contract_addr).or_default(),
+        };
+        *fee_amount = fee_amount
+            .checked_add(order.escrowed_fee()?)
+            .map_err(|_| StdError::generic_err("combined offer and fee amount overflows"))?;
+    }
+
+    for (denom, amount) in native_amounts.into_iter() {
+        Asset {
+            info: AssetInfo::NativeToken { denom },
+            amount,
+        }
+        .assert_sent_native_token_balance(info)?;
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (contract_addr, amount) in token_amounts.into_iter() {
+        if amount.is_zero() {
+            continue;
+        }
+
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: owner.to_string(),
+                recipient: env.contract.address.to_string(),
+                amount,
+            })?,
+        }));
+    }
+
+    Ok(messages)
 }
 
-pub fn cancel_order(deps: DepsMut, info: MessageInfo, order_id: u64) -> StdResult<Response> {
+pub fn cancel_order(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    oracle: &dyn PairOracle,
+    order_id: u64,
+    rebate_as_offer: Option<bool>,
+    refund_as: Option<AssetInfo>,
+) -> StdResult<Response> {
     let config: Config = CONFIG.load(deps.storage)?;
     let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
     if order.bidder_addr != info.sender {
         return Err(StdError::generic_err("unauthorized"));
     }
+    assert_min_lifetime(&config, &order, env.block.time.seconds())?;
 
-    // refund offer asset
-    let mut messages: Vec<CosmosMsg> = vec![order
-        .offer_asset
-        .clone()
-        .into_msg(&deps.querier, order.bidder_addr.clone())?];
-
-    // refund fee
+    // refund fee and fee bond
     let refund_fee_asset = Asset {
-        info: AssetInfo::Token {
-            contract_addr: config.fee_token.to_string(),
-        },
-        amount: order.fee_amount,
+        info: config.fee_asset_info(&order.ask_asset.info),
+        amount: order.escrowed_fee()?,
+    };
+
+    // swap the fee into the offer asset when requested and a pair exists,
+    // otherwise refund the fee token as is
+    let rebate_pair: Option<PairInfo> =
+        if rebate_as_offer.unwrap_or(false) && !config.fee_denominated_in_ask {
+            oracle
+                .query_pair_info(
+                    &deps.querier,
+                    config.terraswap_factory.clone(),
+                    &[
+                        refund_fee_asset.info.clone(),
+                        order.offer_asset.info.clone(),
+                    ],
+                )
+                .ok()
+        } else {
+            None
+        };
+
+    // swap the offer into refund_as when a pair exists, otherwise refund the offer as is.
+    // grouped orders are refunded from the group escrow
+    let refund_as_pair: Option<PairInfo> = match refund_as.clone() {
+        Some(refund_as) if order.group_id.is_none() && refund_as != order.offer_asset.info => {
+            oracle
+                .query_pair_info(
+                    &deps.querier,
+                    config.terraswap_factory.clone(),
+                    &[order.offer_asset.info.clone(), refund_as],
+                )
+                .ok()
+        }
+        _ => None,
     };
-    messages.push(
-        refund_fee_asset
-            .clone()
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut fee_refunded = false;
+    if let Some(refund_as_pair) = refund_as_pair.clone() {
+        let mut refund_asset: Asset = order.escrowed_offer();
+        withhold_native_dust(deps.storage, &mut refund_asset)?;
+        if !refund_asset.amount.is_zero() {
+            messages.push(swap_to_msg(
+                &deps.querier,
+                refund_asset,
+                deps.api
+                    .addr_validate(refund_as_pair.contract_addr.as_str())?,
+                &order.bidder_addr,
+                config.max_spread,
+            )?);
+        }
+    } else if rebate_pair.is_none()
+        && order.group_id.is_none()
+        && order.offer_asset.info == refund_fee_asset.info
+    {
+        // the offer is the fee token, refund both at once
+        let mut refund_asset: Asset = order.escrowed_offer();
+        withhold_native_dust(deps.storage, &mut refund_asset)?;
+        messages.push(
+            Asset {
+                amount: refund_asset.amount.checked_add(refund_fee_asset.amount)?,
+                ..refund_fee_asset.clone()
+            }
             .into_msg(&deps.querier, order.bidder_addr.clone())?,
-    );
+        );
+        fee_refunded = true;
+    } else {
+        messages.extend(refund_offer(deps.storage, &deps.querier, &order)?);
+    }
+
+    if let Some(rebate_pair) = rebate_pair.clone() {
+        messages.push(swap_to_msg(
+            &deps.querier,
+            refund_fee_asset.clone(),
+            deps.api.addr_validate(rebate_pair.contract_addr.as_str())?,
+            &order.bidder_addr,
+            config.max_spread,
+        )?);
+    } else if !fee_refunded {
+        messages.push(
+            refund_fee_asset
+                .clone()
+                .into_msg(&deps.querier, order.bidder_addr.clone())?,
+        );
+    }
+
+    remove_order(deps.storage, &order)?;
+
+    // tells voluntary cancels apart from pulling an order before it expires
+    let mut expiry_attrs: Vec<Attribute> = vec![];
+    if let Some(expires_at) = order.expires_at {
+        let now: u64 = env.block.time.seconds();
+        expiry_attrs.push(attr("was_expired", order.is_expired(now).to_string()));
+        expiry_attrs.push(attr(
+            "seconds_to_expiry",
+            expires_at.saturating_sub(now).to_string(),
+        ));
+    }
+
+    Ok(Response::new()
+        .add_submessages(
+            messages
+                .into_iter()
+                .map(|msg| SubMsg::reply_on_error(msg, CANCEL_ORDER_REPLY_ID + order_id)),
+        )
+        .add_attributes(vec![
+            attr("action", "cancel_order"),
+            attr("order_id", order_id.to_string()),
+            attr("refunded_asset", order.escrowed_offer().to_string()),
+            attr("refunded_fee", refund_fee_asset.to_string()),
+            attr("fee_rebate_as_offer", rebate_pair.is_some().to_string()),
+            attr(
+                "refunded_as",
+                match refund_as_pair {
+                    Some(_) => refund_as.map_or("none".to_string(), |info| info.to_string()),
+                    None => order.offer_asset.info.to_string(),
+                },
+            ),
+        ])
+        .add_attributes(expiry_attrs))
+}
+
+pub fn cancel_user_pair_orders(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pair_addr: String,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    let orders: Vec<OrderInfo> =
+        read_orders_by_user_pair(deps.storage, &info.sender, &pair_addr, limit)?;
+
+    // refund offer assets
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut refund_fee_assets: Vec<Asset> = vec![];
+    for order in orders.iter() {
+        assert_min_lifetime(&config, order, env.block.time.seconds())?;
+        messages.extend(refund_offer(deps.storage, &deps.querier, order)?);
+        merge_asset(
+            &mut refund_fee_assets,
+            Asset {
+                info: config.fee_asset_info(&order.ask_asset.info),
+                amount: order.escrowed_fee()?,
+            },
+        )?;
+        remove_order(deps.storage, order)?;
+    }
 
-    remove_order(deps.storage, &order);
+    // refund fees at once, per fee asset
+    for refund_fee_asset in refund_fee_assets.iter() {
+        if !refund_fee_asset.amount.is_zero() {
+            messages.push(
+                refund_fee_asset
+                    .clone()
+                    .into_msg(&deps.querier, info.sender.clone())?,
+            );
+        }
+    }
 
     Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "cancel_order"),
+        attr("action", "cancel_user_pair_orders"),
+        attr("pair_addr", pair_addr.to_string()),
+        attr(
+            "order_ids",
+            orders
+                .iter()
+                .map(|order| order.order_id.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+        attr(
+            "refunded_fee",
+            refund_fee_assets
+                .iter()
+                .map(|asset| asset.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+    ]))
+}
+
+pub fn top_up_fee(
+    deps: DepsMut,
+    config: &Config,
+    token_addr: Addr,
+    sender: Addr,
+    order_id: u64,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let mut order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+    if order.bidder_addr != sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // only the fee asset of the order can be topped up
+    let token_info = AssetInfo::Token {
+        contract_addr: token_addr.to_string(),
+    };
+    if config.fee_asset_info(&order.ask_asset.info) != token_info {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    order.fee_amount = order.fee_amount.checked_add(amount)?;
+    // the escrowed fee must stay refundable
+    order.escrowed_fee()?;
+    // the fee is hashed
+    order.order_hash = order.compute_hash();
+    ORDERS.save(deps.storage, &order_id.to_be_bytes(), &order)?;
+    increase_open_fees(deps.storage, &order, amount)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "top_up_fee"),
+        attr("order_id", order_id.to_string()),
+        attr("fee_amount", order.fee_amount.to_string()),
+        attr("order_hash", order.order_hash),
+    ]))
+}
+
+pub fn retarget_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    oracle: &dyn PairOracle,
+    order_id: u64,
+    new_pair_offer_asset: AssetInfo,
+    new_pair_ask_asset: AssetInfo,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+    if order.bidder_addr != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // the group escrow is tied to the pair of the group
+    if order.group_id.is_some() {
+        return Err(StdError::generic_err(
+            "orders of a group cannot be retargeted",
+        ));
+    }
+
+    if new_pair_offer_asset != order.offer_asset.info || new_pair_ask_asset != order.ask_asset.info
+    {
+        return Err(StdError::generic_err(
+            "assets do not match the order assets",
+        ));
+    }
+
+    let (factory_addr, pair_info): (Addr, PairInfo) = oracle.query_pair_info_with_fallback(
+        &deps.querier,
+        config.terraswap_factory,
+        config.fallback_factory,
+        &[new_pair_offer_asset.clone(), new_pair_ask_asset.clone()],
+    )?;
+    assert_pair_assets(&pair_info, &[new_pair_offer_asset, new_pair_ask_asset])?;
+    let pair_addr = deps.api.addr_validate(pair_info.contract_addr.as_str())?;
+    assert_pair_not_paused(deps.storage, &pair_addr)?;
+    if pair_addr == order.pair_addr {
+        return Err(StdError::generic_err("order already targets the pair"));
+    }
+
+    // re-index the order under the new pair
+    remove_order(deps.storage, &order)?;
+    let retargeted_order = OrderInfo {
+        factory_addr,
+        pair_addr: pair_addr.clone(),
+        ..order.clone()
+    };
+    store_order(deps.storage, &retargeted_order)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "retarget_order"),
+        attr("order_id", order_id.to_string()),
+        attr("old_pair_addr", order.pair_addr.to_string()),
+        attr("pair_addr", pair_addr.to_string()),
+    ]))
+}
+
+pub fn update_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    order_id: u64,
+    reserve_price: Decimal,
+) -> StdResult<Response> {
+    let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+    if order.bidder_addr != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // lowering the reserve would let the bidder drop the ask in front of a fill
+    if matches!(order.reserve_price, Some(current) if reserve_price < current) {
+        return Err(StdError::generic_err("reserve price can only be raised"));
+    }
+
+    // the ask is raised to the reserve, never lowered below the current ask
+    let reserve_amount: Uint128 = checked_mul_decimal(order.offer_asset.amount, reserve_price)
+        .ok_or_else(|| StdError::generic_err("ask amount at the reserve price overflows"))?;
+    let ask_amount: Uint128 = order.ask_asset.amount.max(reserve_amount);
+
+    // re-index the order under its new price
+    remove_order(deps.storage, &order)?;
+    let mut updated_order = OrderInfo {
+        ask_asset: Asset {
+            info: order.ask_asset.info.clone(),
+            amount: ask_amount,
+        },
+        reserve_price: Some(reserve_price),
+        ..order.clone()
+    };
+    // the ask is hashed
+    updated_order.order_hash = updated_order.compute_hash();
+    store_order(deps.storage, &updated_order)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_order"),
         attr("order_id", order_id.to_string()),
-        attr("refunded_asset", order.offer_asset.to_string()),
-        attr("refunded_fee", refund_fee_asset.to_string()),
+        attr("reserve_price", reserve_price.to_string()),
+        attr("ask_asset", updated_order.ask_asset.to_string()),
+        attr("order_hash", updated_order.order_hash),
     ]))
 }
 
-pub fn execute_order(deps: DepsMut, info: MessageInfo, order_id: u64) -> StdResult<Response> {
+pub fn execute_order(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    oracle: &dyn PairOracle,
+    order_id: u64,
+    executor_recipient: Option<String>,
+    min_return: Option<Uint128>,
+) -> StdResult<Response> {
     let config: Config = CONFIG.load(deps.storage)?;
     let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+    assert_executable(
+        deps.storage,
+        &config,
+        &order,
+        &info.sender,
+        env.block.time.seconds(),
+    )?;
 
     // deduct tax if native
     let offer_asset = if order.offer_asset.is_native_token() {
@@ -134,81 +943,628 @@ pub fn execute_order(deps: DepsMut, info: MessageInfo, order_id: u64) -> StdResu
         order.offer_asset.clone()
     };
 
-    let simul_res: SimulationResponse =
-        simulate(&deps.querier, order.pair_addr.clone(), &offer_asset)?;
-    if simul_res.return_amount < order.ask_asset.amount {
-        return Err(StdError::generic_err("insufficient return amount"));
+    // resolve the pair at execution time, the factory may have redeployed it since submission
+    let pair_info: PairInfo = oracle
+        .query_pair_info(
+            &deps.querier,
+            order.factory_addr.clone(),
+            &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+        )
+        .map_err(|_| {
+            StdError::generic_err("there is no terraswap pair for the 2 assets provided")
+        })?;
+    // the pair picks the swap direction from the offer asset info, whatever the order of its
+    // asset_infos, so the simulation is in the order direction once the pair trades both assets
+    assert_pair_assets(
+        &pair_info,
+        &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+    )?;
+    let pair_addr = deps.api.addr_validate(pair_info.contract_addr.as_str())?;
+    assert_pair_not_paused(deps.storage, &pair_addr)?;
+
+    // an executor passing the return it expects has simulated off-chain,
+    // the actual return is checked against it in the reply
+    if min_return.is_none() {
+        let simul_res: SimulationResponse =
+            oracle.simulate(&deps.querier, pair_addr.clone(), &offer_asset)?;
+        if simul_res.return_amount < order.ask_asset.amount {
+            return Err(StdError::generic_err("insufficient return amount"));
+        }
     }
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+    // the swap output is sent to the contract and measured against this balance in the reply
+    let ask_balance_before: Uint128 = order
+        .ask_asset
+        .info
+        .query_pool(&deps.querier, env.contract.address.clone())?;
 
-    // create swap message
-    match offer_asset.clone().info {
-        AssetInfo::Token { contract_addr } => {
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr,
-                funds: vec![],
-                msg: to_binary(&Cw20ExecuteMsg::Send {
-                    contract: order.pair_addr.to_string(),
-                    amount: offer_asset.amount,
-                    msg: to_binary(&PairCw20HookMsg::Swap {
-                        to: None,
-                        belief_price: None,
-                        max_spread: None,
-                    })?,
-                })?,
-            }));
-        }
-        AssetInfo::NativeToken { denom } => {
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: order.pair_addr.to_string(),
-                funds: vec![Coin {
-                    denom,
-                    amount: offer_asset.amount,
-                }],
-                msg: to_binary(&PairExecuteMsg::Swap {
-                    offer_asset,
+    // create swap message, the order is settled in the reply once the swap succeeded
+    let max_spread: Decimal = order.max_spread.unwrap_or(config.max_spread);
+    let swap_msg: CosmosMsg = match offer_asset.clone().info {
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: pair_addr.to_string(),
+                amount: offer_asset.amount,
+                msg: to_binary(&PairCw20HookMsg::Swap {
+                    to: Some(env.contract.address.to_string()),
                     belief_price: None,
-                    max_spread: None,
-                    to: None,
+                    max_spread: Some(max_spread),
                 })?,
-            }));
-        }
+            })?,
+        }),
+        AssetInfo::NativeToken { denom } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: pair_addr.to_string(),
+            funds: vec![Coin {
+                denom,
+                amount: offer_asset.amount,
+            }],
+            msg: to_binary(&PairExecuteMsg::Swap {
+                offer_asset,
+                belief_price: None,
+                max_spread: Some(max_spread),
+                to: Some(env.contract.address.to_string()),
+            })?,
+        }),
     };
 
-    // send asset to bidder
-    messages.push(
-        order
-            .ask_asset
-            .clone()
-            .into_msg(&deps.querier, order.bidder_addr.clone())?,
-    );
+    PENDING_EXECUTION.save(
+        deps.storage,
+        &PendingExecution {
+            order_id: order.order_id,
+            recipient: match executor_recipient {
+                Some(recipient) => deps.api.addr_validate(&recipient)?,
+                None => info.sender.clone(),
+            },
+            executor: info.sender,
+            ask_balance_before,
+            min_return,
+        },
+    )?;
+
+    let res = Response::new()
+        .add_submessage(SubMsg::reply_always(swap_msg, EXECUTE_ORDER_REPLY_ID))
+        .add_attributes(vec![
+            attr("action", "execute_order"),
+            attr("order_id", order.order_id.to_string()),
+            attr("pair_addr", pair_addr.to_string()),
+        ]);
+
+    // rough gas hint for keepers, the settlement reports its own count
+    let messages_count: usize = res.messages.len();
+    Ok(res.add_attribute("messages_count", messages_count.to_string()))
+}
+
+/// Settles an order once its swap is done: pays the bidder and the executor and removes the order.
+/// If the swap failed, the order is left untouched and escrowed, and the reason is reported.
+pub fn execute_order_reply(
+    deps: DepsMut,
+    env: Env,
+    result: ContractResult<SubMsgExecutionResponse>,
+) -> StdResult<Response> {
+    let pending: PendingExecution = PENDING_EXECUTION.load(deps.storage)?;
+    PENDING_EXECUTION.remove(deps.storage);
+
+    if let ContractResult::Err(reason) = result {
+        return Ok(Response::new().add_attributes(vec![
+            attr("action", "fill_failed"),
+            attr("order_id", pending.order_id.to_string()),
+            attr("reason", reason),
+        ]));
+    }
+
+    let config: Config = CONFIG.load(deps.storage)?;
+    let order: OrderInfo = ORDERS.load(deps.storage, &pending.order_id.to_be_bytes())?;
+
+    // verify the actual output of the swap before paying out
+    let return_amount: Uint128 = order
+        .ask_asset
+        .info
+        .query_pool(&deps.querier, env.contract.address.clone())?
+        .checked_sub(pending.ask_balance_before)?;
+    if matches!(pending.min_return, Some(min_return) if return_amount < min_return) {
+        return Err(StdError::generic_err(
+            "return amount is less than min_return",
+        ));
+    }
+    let payout: FillPayout = order.fill_payout(&config, return_amount, env.block.time.seconds())?;
+    let bidder_asset = Asset {
+        info: order.ask_asset.info.clone(),
+        amount: payout.bidder_amount,
+    };
+
+    // send asset to bidder, or resubmit it as the mirrored order of a grid order.
+    // The mirrored order is skipped once its assets are blocked, its pair paused or its
+    // ask amount at the rebuy price overflows
+    let mirror_grid: Option<(OrderGrid, Uint128)> = order
+        .grid
+        .clone()
+        .filter(|_| {
+            assert_assets_not_blocked(
+                deps.storage,
+                &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+            )
+            .is_ok()
+                && assert_pair_not_paused(deps.storage, &order.pair_addr).is_ok()
+        })
+        .and_then(|grid| {
+            checked_mul_decimal(bidder_asset.amount, grid.rebuy_price)
+                .map(|rebuy_amount| (grid, rebuy_amount))
+        });
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut mirror_order_id: Option<u64> = None;
+    if let Some((grid, rebuy_amount)) = mirror_grid {
+        let mut mirror_order = OrderInfo {
+            order_id: 0u64, // provisional
+            bidder_addr: order.bidder_addr.clone(),
+            factory_addr: order.factory_addr.clone(),
+            pair_addr: order.pair_addr.clone(),
+            offer_asset: bidder_asset.clone(),
+            ask_asset: Asset {
+                info: order.offer_asset.info.clone(),
+                amount: rebuy_amount,
+            },
+            offer_decimals: order.ask_decimals,
+            ask_decimals: order.offer_decimals,
+            fee_amount: grid.rebuy_fee,
+            fee_bond: Uint128::zero(),
+            created_at: env.block.time.seconds(),
+            expires_at: config
+                .default_order_ttl_seconds
+                .map(|ttl| env.block.time.seconds() + ttl),
+            group_id: None,
+            order_hash: String::new(), // provisional
+            grid: None,
+            offer_fee: Uint128::zero(),
+            ramp_seconds: None,
+            fill_hook: None,
+            reserve_price: None,
+            max_spread: order.max_spread,
+        };
+        store_new_order(deps.storage, &mut mirror_order)?;
+        mirror_order_id = Some(mirror_order.order_id);
+    } else {
+        messages.push(
+            bidder_asset
+                .clone()
+                .into_msg(&deps.querier, order.bidder_addr.clone())?,
+        );
+
+        // the fee escrowed for the skipped mirrored order goes back to the bidder
+        if let Some(grid) = order.grid.clone() {
+            if !grid.rebuy_fee.is_zero() {
+                messages.push(
+                    Asset {
+                        info: config.fee_asset_info(&order.ask_asset.info),
+                        amount: grid.rebuy_fee,
+                    }
+                    .into_msg(&deps.querier, order.bidder_addr.clone())?,
+                );
+            }
+        }
+    }
 
     // send excess to executor
-    let excess_amount: Uint128 = simul_res.return_amount - order.ask_asset.amount;
-    if excess_amount > Uint128::zero() {
+    if payout.excess_amount > Uint128::zero() {
         let excess_asset = Asset {
-            amount: excess_amount,
+            amount: payout.excess_amount,
             info: order.ask_asset.info.clone(),
         };
-        messages.push(excess_asset.into_msg(&deps.querier, info.sender.clone())?);
+        messages.push(excess_asset.into_msg(&deps.querier, pending.recipient.clone())?);
+    }
+
+    let fee_asset_info: AssetInfo = config.fee_asset_info(&order.ask_asset.info);
+    let refund_bond_asset = Asset {
+        amount: payout.refund_bond_amount,
+        info: fee_asset_info.clone(),
+    };
+
+    // the treasury takes its share of the fee
+    if let Some(treasury) = config.treasury.clone() {
+        if !payout.protocol_fee_amount.is_zero() {
+            messages.push(
+                Asset {
+                    amount: payout.protocol_fee_amount,
+                    info: fee_asset_info.clone(),
+                }
+                .into_msg(&deps.querier, treasury)?,
+            );
+        }
+    }
+
+    // the bidder is credited its rebate, claimed later to avoid dust transfers
+    let rebate_amount: Uint128 = payout.rebate_amount;
+    if !rebate_amount.is_zero() {
+        REBATES.update(
+            deps.storage,
+            order.bidder_addr.as_bytes(),
+            |rebates: Option<Uint128>| -> StdResult<Uint128> {
+                Ok(rebates.unwrap_or_default().checked_add(rebate_amount)?)
+            },
+        )?;
+    }
+    // send the rest of the fee to executor, nothing is left when the protocol fee
+    // and the rebate take it all
+    if !payout.executor_fee_amount.is_zero() {
+        messages.push(
+            Asset {
+                amount: payout.executor_fee_amount,
+                info: fee_asset_info.clone(),
+            }
+            .into_msg(&deps.querier, pending.recipient.clone())?,
+        );
+    }
+
+    // send the fee taken out of the offer to executor
+    if !order.offer_fee.is_zero() {
+        messages.push(
+            Asset {
+                info: order.offer_asset.info.clone(),
+                amount: order.offer_fee,
+            }
+            .into_msg(&deps.querier, pending.recipient.clone())?,
+        );
+    }
+
+    // pay the bounty while the pool lasts
+    let bounty_pool: Uint128 = BOUNTY_POOL.load(deps.storage)?;
+    let bounty_amount: Uint128 = config.bounty_per_fill.min(bounty_pool);
+    if !bounty_amount.is_zero() {
+        BOUNTY_POOL.save(deps.storage, &bounty_pool.checked_sub(bounty_amount)?)?;
+        messages.push(
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: config.fee_token.to_string(),
+                },
+                amount: bounty_amount,
+            }
+            .into_msg(&deps.querier, pending.recipient.clone())?,
+        );
+    }
+
+    // pay the incentive while the pool lasts
+    let mut incentive_amount = Uint128::zero();
+    if let Some(incentive_token) = config.incentive_token.clone() {
+        let incentive_pool: Uint128 = INCENTIVE_POOL.load(deps.storage)?;
+        incentive_amount = config.incentive_per_fill.min(incentive_pool);
+        if !incentive_amount.is_zero() {
+            INCENTIVE_POOL.save(deps.storage, &incentive_pool.checked_sub(incentive_amount)?)?;
+            messages.push(
+                Asset {
+                    info: AssetInfo::Token {
+                        contract_addr: incentive_token.to_string(),
+                    },
+                    amount: incentive_amount,
+                }
+                .into_msg(&deps.querier, pending.recipient.clone())?,
+            );
+        }
+    }
+
+    // refund the unreleased fee bond to the bidder
+    if !refund_bond_asset.amount.is_zero() {
+        messages.push(
+            refund_bond_asset
+                .clone()
+                .into_msg(&deps.querier, order.bidder_addr.clone())?,
+        );
+    }
+
+    // draw the filled amount from the group escrow
+    if let Some(group_id) = order.group_id {
+        messages.extend(release_group_order(
+            deps.storage,
+            &deps.querier,
+            &order,
+            group_id,
+            true,
+        )?);
+    }
+
+    // executor stats
+    if config.max_fee_per_epoch.is_some() {
+        let epoch: u64 = config.epoch(env.block.time.seconds());
+        FEE_EARNED_EPOCH.update(
+            deps.storage,
+            (pending.executor.as_bytes(), &epoch.to_be_bytes()),
+            |earned: Option<Uint128>| -> StdResult<Uint128> {
+                Ok(earned.unwrap_or_default().checked_add(payout.fee_amount)?)
+            },
+        )?;
+    }
+    LAST_FILL_BY_EXECUTOR.save(deps.storage, pending.executor.as_bytes(), &order.order_id)?;
+    FILLS_BY_EXECUTOR.update(
+        deps.storage,
+        pending.executor.as_bytes(),
+        |fills: Option<u64>| -> StdResult<u64> { Ok(fills.unwrap_or(0u64) + 1u64) },
+    )?;
+
+    // pair stats
+    FILLS_BY_PAIR.update(
+        deps.storage,
+        order.pair_addr.as_bytes(),
+        |fills: Option<u64>| -> StdResult<u64> { Ok(fills.unwrap_or(0u64) + 1u64) },
+    )?;
+    FEES_BY_PAIR.update(
+        deps.storage,
+        order.pair_addr.as_bytes(),
+        |fees: Option<Uint128>| -> StdResult<Uint128> {
+            Ok(fees.unwrap_or_default().checked_add(payout.fee_amount)?)
+        },
+    )?;
+
+    remove_order(deps.storage, &order)?;
+    if config.retain_fills {
+        FILLS.save(
+            deps.storage,
+            (order.pair_addr.as_bytes(), &order.order_id.to_be_bytes()),
+            &FillInfo {
+                order: order.clone(),
+                filled_return: return_amount,
+                executor: pending.executor.clone(),
+                filled_at: env.block.time.seconds(),
+            },
+        )?;
+    }
+
+    // notify the hook last, its failure is reported by fill_hook_reply
+    let mut submessages: Vec<SubMsg> = vec![];
+    if let Some(fill_hook) = order.fill_hook.clone() {
+        submessages.push(SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: fill_hook.contract,
+                funds: vec![],
+                msg: fill_hook.msg,
+            }),
+            FILL_HOOK_REPLY_ID,
+        ));
+    }
+
+    let messages_count: usize = messages.len() + submessages.len();
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(submessages)
+        .add_attributes(vec![
+            attr("action", "settle_order"),
+            attr("messages_count", messages_count.to_string()),
+            attr("order_id", order.order_id.to_string()),
+            attr("executor", pending.executor.to_string()),
+            attr("recipient", pending.recipient.to_string()),
+            attr("fee_amount", payout.fee_amount.to_string()),
+            attr(
+                "protocol_fee_amount",
+                payout.protocol_fee_amount.to_string(),
+            ),
+            attr("rebate_amount", rebate_amount.to_string()),
+            attr("bounty_amount", bounty_amount.to_string()),
+            attr("incentive_amount", incentive_amount.to_string()),
+            attr("refunded_bond", refund_bond_asset.amount.to_string()),
+            attr("excess_amount", payout.excess_amount.to_string()),
+            attr(
+                "mirror_order_id",
+                mirror_order_id.map_or("none".to_string(), |id| id.to_string()),
+            ),
+        ]))
+}
+
+/// Keeps the fill when the fill hook of the order fails, reporting the reason
+pub fn fill_hook_reply(result: ContractResult<SubMsgExecutionResponse>) -> StdResult<Response> {
+    match result {
+        ContractResult::Ok(_) => Ok(Response::default()),
+        ContractResult::Err(reason) => Ok(Response::new().add_attributes(vec![
+            attr("action", "fill_hook_failed"),
+            attr("reason", reason),
+        ])),
+    }
+}
+
+/// Surfaces which order a failed cancel refund belongs to. The tx is still reverted.
+pub fn cancel_order_reply(
+    order_id: u64,
+    result: ContractResult<SubMsgExecutionResponse>,
+) -> StdResult<Response> {
+    match result {
+        ContractResult::Ok(_) => Ok(Response::default()),
+        ContractResult::Err(reason) => Err(StdError::generic_err(format!(
+            "refund of order {} failed: {}",
+            order_id, reason
+        ))),
+    }
+}
+
+pub fn claim_rebates(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let rebate_amount: Uint128 = REBATES
+        .may_load(deps.storage, info.sender.as_bytes())?
+        .unwrap_or_default();
+    if rebate_amount.is_zero() {
+        return Err(StdError::generic_err("no rebates to claim"));
     }
+    REBATES.remove(deps.storage, info.sender.as_bytes());
 
-    // send fee to executor
-    let fee_asset = Asset {
-        amount: order.fee_amount,
+    let rebate_asset = Asset {
         info: AssetInfo::Token {
             contract_addr: config.fee_token.to_string(),
         },
+        amount: rebate_amount,
     };
-    messages.push(fee_asset.clone().into_msg(&deps.querier, info.sender)?);
 
-    remove_order(deps.storage, &order);
+    Ok(Response::new()
+        .add_message(rebate_asset.clone().into_msg(&deps.querier, info.sender)?)
+        .add_attributes(vec![
+            attr("action", "claim_rebates"),
+            attr("rebate_asset", rebate_asset.to_string()),
+        ]))
+}
+
+pub fn heartbeat(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
+    let now: u64 = env.block.time.seconds();
+    LAST_HEARTBEAT.save(deps.storage, info.sender.as_bytes(), &now)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "heartbeat"),
+        attr("executor", info.sender.to_string()),
+        attr("timestamp", now.to_string()),
+    ]))
+}
+
+pub fn cleanup_expired_orders(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    order_ids: Vec<u64>,
+) -> StdResult<Response> {
+    if order_ids.len() > MAX_CLEANUP_ORDERS {
+        return Err(StdError::generic_err(format!(
+            "cannot clean up more than {} orders at once",
+            MAX_CLEANUP_ORDERS
+        )));
+    }
+
+    let config: Config = CONFIG.load(deps.storage)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut reward_assets: Vec<Asset> = vec![];
+    let mut cleaned_order_ids: Vec<String> = vec![];
+    for order_id in order_ids {
+        // live orders, and orders already gone, are skipped so that one stale id
+        // doesn't fail the batch
+        let order: OrderInfo = match ORDERS.may_load(deps.storage, &order_id.to_be_bytes())? {
+            Some(order) if order.is_expired(env.block.time.seconds()) => order,
+            _ => continue,
+        };
+
+        // refund offer asset
+        messages.extend(refund_offer(deps.storage, &deps.querier, &order)?);
+
+        // split the fee between the caller and the bidder
+        let reward_amount: Uint128 = order
+            .fee_amount
+            .multiply_ratio(config.cleanup_reward_bps, 10000u64);
+        let reward_asset = Asset {
+            info: config.fee_asset_info(&order.ask_asset.info),
+            amount: reward_amount,
+        };
+        let refund_fee_asset = Asset {
+            amount: order.escrowed_fee()?.checked_sub(reward_amount)?,
+            ..reward_asset.clone()
+        };
+        if !refund_fee_asset.amount.is_zero() {
+            messages.push(refund_fee_asset.into_msg(&deps.querier, order.bidder_addr.clone())?);
+        }
+        merge_asset(&mut reward_assets, reward_asset)?;
+
+        remove_order(deps.storage, &order)?;
+        cleaned_order_ids.push(order_id.to_string());
+    }
+
+    // the caller is paid the rewards of the whole batch at once
+    for reward_asset in reward_assets.iter() {
+        if !reward_asset.amount.is_zero() {
+            messages.push(
+                reward_asset
+                    .clone()
+                    .into_msg(&deps.querier, info.sender.clone())?,
+            );
+        }
+    }
 
     Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "execute_order"),
-        attr("order_id", order.order_id.to_string()),
-        attr("fee_amount", fee_asset.amount.to_string()),
-        attr("excess_amount", excess_amount.to_string()),
+        attr("action", "cleanup_expired_orders"),
+        attr("order_ids", cleaned_order_ids.join(",")),
+        attr(
+            "cleanup_reward",
+            reward_assets
+                .iter()
+                .map(|asset| asset.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
     ]))
 }
+
+// adds the asset to the list, merged with the entry of the same asset if any
+fn merge_asset(assets: &mut Vec<Asset>, asset: Asset) -> StdResult<()> {
+    match assets.iter_mut().find(|entry| entry.info == asset.info) {
+        Some(entry) => entry.amount = entry.amount.checked_add(asset.amount)?,
+        None => assets.push(asset),
+    }
+
+    Ok(())
+}
+
+/// Swaps the asset through the pair, sending the return to the recipient
+fn swap_to_msg(
+    querier: &QuerierWrapper,
+    asset: Asset,
+    pair_addr: Addr,
+    recipient: &Addr,
+    max_spread: Decimal,
+) -> StdResult<CosmosMsg> {
+    match asset.info.clone() {
+        AssetInfo::Token { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: pair_addr.to_string(),
+                amount: asset.amount,
+                msg: to_binary(&PairCw20HookMsg::Swap {
+                    to: Some(recipient.to_string()),
+                    belief_price: None,
+                    max_spread: Some(max_spread),
+                })?,
+            })?,
+        })),
+        AssetInfo::NativeToken { denom } => {
+            let amount: Uint128 = asset.deduct_tax(querier)?.amount;
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: pair_addr.to_string(),
+                funds: vec![Coin { denom, amount }],
+                msg: to_binary(&PairExecuteMsg::Swap {
+                    offer_asset: Asset { amount, ..asset },
+                    belief_price: None,
+                    max_spread: Some(max_spread),
+                    to: Some(recipient.to_string()),
+                })?,
+            }))
+        }
+    }
+}
+
+// withholds the native refund dust of a refund for the protocol, every native offer
+// refund goes through here whether it is sent as is or swapped first
+pub fn withhold_native_dust(storage: &mut dyn Storage, refund_asset: &mut Asset) -> StdResult<()> {
+    if let AssetInfo::NativeToken { denom } = &refund_asset.info {
+        let config: Config = CONFIG.load(storage)?;
+        let dust: Uint128 = std::cmp::min(config.native_refund_dust, refund_asset.amount);
+        if !dust.is_zero() {
+            ACCRUED_NATIVE_DUST.update(storage, denom.as_bytes(), |accrued| -> StdResult<_> {
+                Ok(accrued.unwrap_or_default().checked_add(dust)?)
+            })?;
+            refund_asset.amount = refund_asset.amount.checked_sub(dust)?;
+        }
+    }
+
+    Ok(())
+}
+
+// refunds the offer asset to the bidder, grouped orders share the escrow of their group
+pub fn refund_offer(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    order: &OrderInfo,
+) -> StdResult<Vec<CosmosMsg>> {
+    if let Some(group_id) = order.group_id {
+        return release_group_order(storage, querier, order, group_id, false);
+    }
+
+    let mut refund_asset: Asset = order.escrowed_offer();
+    withhold_native_dust(storage, &mut refund_asset)?;
+    if refund_asset.amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![
+        refund_asset.into_msg(querier, order.bidder_addr.clone())?
+    ])
+}
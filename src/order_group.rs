@@ -0,0 +1,206 @@
+use crate::msg::{OrderGroupLevel, SubmitOrderItem};
+use crate::order::{build_order, escrow_msgs, withhold_native_dust};
+use crate::querier::PairOracle;
+use crate::state::{
+    assert_min_lifetime, decrease_escrowed_offer, remove_order, store_new_order,
+    store_new_order_group, Config, OrderGroupInfo, OrderInfo, CONFIG, ORDERS, ORDER_GROUPS,
+};
+use cosmwasm_std::{
+    attr, CosmosMsg, DepsMut, Env, MessageInfo, QuerierWrapper, Response, StdError, StdResult,
+    Storage, Uint128,
+};
+use terraswap::asset::{Asset, AssetInfo};
+
+#[allow(clippy::too_many_arguments)]
+pub fn submit_order_group(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    oracle: &dyn PairOracle,
+    offer_asset: Asset,
+    ask_asset_info: AssetInfo,
+    levels: Vec<OrderGroupLevel>,
+    fee_amount: Uint128,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    if levels.is_empty() {
+        return Err(StdError::generic_err(
+            "order group must have at least one level",
+        ));
+    }
+
+    // every level must be fillable from the shared escrow on its own
+    if levels
+        .iter()
+        .any(|level| level.offer_amount > offer_asset.amount)
+    {
+        return Err(StdError::generic_err(
+            "level offer amount must not exceed the escrowed amount",
+        ));
+    }
+
+    // every level is validated as a single order
+    let bidder_addr = deps.api.addr_validate(info.sender.as_str())?;
+    let mut new_orders: Vec<OrderInfo> = vec![];
+    for level in levels.iter() {
+        new_orders.push(build_order(
+            deps.as_ref(),
+            &env,
+            oracle,
+            &config,
+            &bidder_addr,
+            SubmitOrderItem {
+                offer_asset: Asset {
+                    info: offer_asset.info.clone(),
+                    amount: level.offer_amount,
+                },
+                ask_asset: Asset {
+                    info: ask_asset_info.clone(),
+                    amount: level.ask_amount,
+                },
+                fee_amount,
+                expires_at: None,
+                fee_bond: None,
+                grid: None,
+                offer_fee_bps: None,
+                reward_ramp: None,
+                fill_hook: None,
+                max_spread_bps: None,
+            },
+        )?);
+    }
+
+    // the offer is escrowed once for all the levels, the fee of every level is transferred
+    let messages: Vec<CosmosMsg> = escrow_msgs(
+        &env,
+        &info,
+        &bidder_addr,
+        &config,
+        &new_orders,
+        Some(&offer_asset),
+    )?;
+
+    let mut new_group = OrderGroupInfo {
+        group_id: 0u64, // provisional
+        bidder_addr,
+        pair_addr: new_orders[0].pair_addr.clone(),
+        escrow_asset: offer_asset.clone(),
+        order_ids: vec![],
+    };
+    store_new_order_group(deps.storage, &mut new_group)?;
+
+    for new_order in new_orders.iter_mut() {
+        new_order.group_id = Some(new_group.group_id);
+        store_new_order(deps.storage, new_order)?;
+        new_group.order_ids.push(new_order.order_id);
+    }
+    ORDER_GROUPS.save(deps.storage, &new_group.group_id.to_be_bytes(), &new_group)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "submit_order_group"),
+        attr("group_id", new_group.group_id.to_string()),
+        attr("bidder_addr", info.sender.to_string()),
+        attr("offer_asset", offer_asset.to_string()),
+        attr(
+            "order_ids",
+            new_group
+                .order_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+    ]))
+}
+
+pub fn cancel_order_group(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    group_id: u64,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let group: OrderGroupInfo = ORDER_GROUPS.load(deps.storage, &group_id.to_be_bytes())?;
+    if group.bidder_addr != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // refund the fees of the remaining orders, which all ask the same asset
+    let mut refund_fee_asset = Asset {
+        info: AssetInfo::Token {
+            contract_addr: config.fee_token.to_string(),
+        },
+        amount: Uint128::zero(),
+    };
+    for order_id in group.order_ids.iter() {
+        let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+        assert_min_lifetime(&config, &order, env.block.time.seconds())?;
+        refund_fee_asset.info = config.fee_asset_info(&order.ask_asset.info);
+        refund_fee_asset.amount = refund_fee_asset.amount.checked_add(order.fee_amount)?;
+        remove_order(deps.storage, &order)?;
+    }
+    decrease_escrowed_offer(deps.storage, &group.bidder_addr, &group.escrow_asset)?;
+    ORDER_GROUPS.remove(deps.storage, &group_id.to_be_bytes());
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    // refund remaining escrow
+    let mut refund_asset: Asset = group.escrow_asset.clone();
+    withhold_native_dust(deps.storage, &mut refund_asset)?;
+    if !refund_asset.amount.is_zero() {
+        messages.push(refund_asset.into_msg(&deps.querier, group.bidder_addr.clone())?);
+    }
+
+    if !refund_fee_asset.amount.is_zero() {
+        messages.push(
+            refund_fee_asset
+                .clone()
+                .into_msg(&deps.querier, group.bidder_addr.clone())?,
+        );
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "cancel_order_group"),
+        attr("group_id", group_id.to_string()),
+        attr("refunded_asset", group.escrow_asset.to_string()),
+        attr("refunded_fee", refund_fee_asset.to_string()),
+    ]))
+}
+
+/// Detaches an order from its group. When the order was filled, its offer amount is drawn
+/// from the shared escrow. Once the last order of the group is gone, the remaining escrow
+/// is refunded to the bidder and the group is removed.
+pub fn release_group_order(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    order: &OrderInfo,
+    group_id: u64,
+    filled: bool,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut group: OrderGroupInfo = ORDER_GROUPS.load(storage, &group_id.to_be_bytes())?;
+    if filled {
+        group.escrow_asset.amount = group
+            .escrow_asset
+            .amount
+            .checked_sub(order.offer_asset.amount)
+            .map_err(|_| StdError::generic_err("insufficient group escrow"))?;
+        decrease_escrowed_offer(storage, &group.bidder_addr, &order.offer_asset)?;
+    }
+    group.order_ids.retain(|id| *id != order.order_id);
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if group.order_ids.is_empty() {
+        let mut refund_asset: Asset = group.escrow_asset.clone();
+        withhold_native_dust(storage, &mut refund_asset)?;
+        if !refund_asset.amount.is_zero() {
+            messages.push(refund_asset.into_msg(querier, group.bidder_addr.clone())?);
+        }
+        decrease_escrowed_offer(storage, &group.bidder_addr, &group.escrow_asset)?;
+        ORDER_GROUPS.remove(storage, &group_id.to_be_bytes());
+    } else {
+        ORDER_GROUPS.save(storage, &group_id.to_be_bytes(), &group)?;
+    }
+
+    Ok(messages)
+}
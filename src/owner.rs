@@ -0,0 +1,298 @@
+use crate::order::refund_offer;
+use crate::state::{
+    read_orders_by_asset, remove_order, Config, OrderInfo, ACCRUED_NATIVE_DUST, BLOCKED_ASSETS,
+    BOUNTY_POOL, CONFIG, INCENTIVE_POOL, MIN_FEE_BY_PAIR, ORDERS, PAUSED_PAIRS,
+};
+use cosmwasm_std::{
+    attr, to_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terraswap::asset::{Asset, AssetInfo};
+
+pub fn block_asset(deps: DepsMut, info: MessageInfo, asset_info: AssetInfo) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    BLOCKED_ASSETS.save(deps.storage, asset_info.to_string(), &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "block_asset"),
+        attr("asset_info", asset_info.to_string()),
+    ]))
+}
+
+pub fn unblock_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    BLOCKED_ASSETS.remove(deps.storage, asset_info.to_string());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "unblock_asset"),
+        attr("asset_info", asset_info.to_string()),
+    ]))
+}
+
+pub fn force_cancel_order(deps: DepsMut, info: MessageInfo, order_id: u64) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+
+    // refund offer and fees to the bidder
+    let mut messages: Vec<CosmosMsg> = refund_offer(deps.storage, &deps.querier, &order)?;
+    let refund_fee_asset = Asset {
+        info: config.fee_asset_info(&order.ask_asset.info),
+        amount: order.escrowed_fee()?,
+    };
+    if !refund_fee_asset.amount.is_zero() {
+        messages.push(
+            refund_fee_asset
+                .clone()
+                .into_msg(&deps.querier, order.bidder_addr.clone())?,
+        );
+    }
+
+    remove_order(deps.storage, &order)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "force_cancel_order"),
+        attr("order_id", order_id.to_string()),
+        attr("bidder_addr", order.bidder_addr.to_string()),
+        attr("refunded_asset", order.escrowed_offer().to_string()),
+        attr("refunded_fee", refund_fee_asset.to_string()),
+    ]))
+}
+
+pub fn evacuate_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    if !BLOCKED_ASSETS.has(deps.storage, asset_info.to_string()) {
+        return Err(StdError::generic_err("asset must be blocked first"));
+    }
+
+    let orders: Vec<OrderInfo> = read_orders_by_asset(deps.storage, &asset_info, limit)?;
+
+    // refund offer and fees to each bidder
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for order in orders.iter() {
+        messages.extend(refund_offer(deps.storage, &deps.querier, order)?);
+        let refund_fee_asset = Asset {
+            info: config.fee_asset_info(&order.ask_asset.info),
+            amount: order.escrowed_fee()?,
+        };
+        if !refund_fee_asset.amount.is_zero() {
+            messages.push(refund_fee_asset.into_msg(&deps.querier, order.bidder_addr.clone())?);
+        }
+
+        remove_order(deps.storage, order)?;
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "evacuate_asset"),
+        attr("asset_info", asset_info.to_string()),
+        attr(
+            "order_ids",
+            orders
+                .iter()
+                .map(|order| order.order_id.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+    ]))
+}
+
+pub fn pause_pair(deps: DepsMut, info: MessageInfo, pair_addr: String) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    PAUSED_PAIRS.save(deps.storage, pair_addr.as_bytes(), &true)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "pause_pair"),
+        attr("pair_addr", pair_addr.to_string()),
+    ]))
+}
+
+pub fn unpause_pair(deps: DepsMut, info: MessageInfo, pair_addr: String) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    PAUSED_PAIRS.remove(deps.storage, pair_addr.as_bytes());
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "unpause_pair"),
+        attr("pair_addr", pair_addr.to_string()),
+    ]))
+}
+
+pub fn set_pair_min_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    pair_addr: String,
+    min_fee_amount: Option<Uint128>,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    // None falls back to the global min_fee_amount
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    if let Some(min_fee_amount) = min_fee_amount {
+        MIN_FEE_BY_PAIR.save(deps.storage, pair_addr.as_bytes(), &min_fee_amount)?;
+    } else {
+        MIN_FEE_BY_PAIR.remove(deps.storage, pair_addr.as_bytes());
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_pair_min_fee"),
+        attr("pair_addr", pair_addr.to_string()),
+        attr(
+            "min_fee_amount",
+            min_fee_amount.unwrap_or(config.min_fee_amount).to_string(),
+        ),
+    ]))
+}
+
+pub fn deposit_bounty(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let bounty_pool: Uint128 = BOUNTY_POOL.load(deps.storage)?.checked_add(amount)?;
+    BOUNTY_POOL.save(deps.storage, &bounty_pool)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: config.fee_token.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: info.sender.to_string(),
+                recipient: env.contract.address.to_string(),
+                amount,
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "deposit_bounty"),
+            attr("amount", amount.to_string()),
+            attr("bounty_pool", bounty_pool.to_string()),
+        ]))
+}
+
+pub fn deposit_incentive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let incentive_token: Addr = config
+        .incentive_token
+        .ok_or_else(|| StdError::generic_err("no incentive token is configured"))?;
+    let incentive_pool: Uint128 = INCENTIVE_POOL.load(deps.storage)?.checked_add(amount)?;
+    INCENTIVE_POOL.save(deps.storage, &incentive_pool)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: incentive_token.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: info.sender.to_string(),
+                recipient: env.contract.address.to_string(),
+                amount,
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "deposit_incentive"),
+            attr("amount", amount.to_string()),
+            attr("incentive_pool", incentive_pool.to_string()),
+        ]))
+}
+
+pub fn set_bounty_per_fill(
+    deps: DepsMut,
+    info: MessageInfo,
+    bounty_per_fill: Uint128,
+) -> StdResult<Response> {
+    let mut config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    config.bounty_per_fill = bounty_per_fill;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_bounty_per_fill"),
+        attr("bounty_per_fill", bounty_per_fill.to_string()),
+    ]))
+}
+
+pub fn sweep_native_dust(deps: DepsMut, info: MessageInfo, denom: String) -> StdResult<Response> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let amount: Uint128 = ACCRUED_NATIVE_DUST
+        .may_load(deps.storage, denom.as_bytes())?
+        .unwrap_or_default();
+    if amount.is_zero() {
+        return Err(StdError::generic_err("no dust accrued in denom"));
+    }
+    ACCRUED_NATIVE_DUST.remove(deps.storage, denom.as_bytes());
+
+    let recipient = config.treasury.unwrap_or(config.owner);
+    let dust_asset = Asset {
+        info: AssetInfo::NativeToken { denom },
+        amount,
+    };
+
+    Ok(Response::new()
+        .add_message(
+            dust_asset
+                .clone()
+                .into_msg(&deps.querier, recipient.clone())?,
+        )
+        .add_attributes(vec![
+            attr("action", "sweep_native_dust"),
+            attr("recipient", recipient.to_string()),
+            attr("dust_asset", dust_asset.to_string()),
+        ]))
+}
@@ -0,0 +1,126 @@
+use cosmwasm_std::{Addr, QuerierWrapper, StdError, StdResult, Uint128};
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use terraswap::asset::{Asset, AssetInfo, PairInfo};
+use terraswap::pair::{PoolResponse, QueryMsg as PairQueryMsg, SimulationResponse};
+use terraswap::querier::{query_pair_info, simulate};
+
+// decimals of the terra native denoms
+const NATIVE_TOKEN_DECIMALS: u8 = 6;
+
+pub fn query_decimals(querier: &QuerierWrapper, asset_info: &AssetInfo) -> StdResult<u8> {
+    match asset_info {
+        AssetInfo::NativeToken { .. } => Ok(NATIVE_TOKEN_DECIMALS),
+        AssetInfo::Token { contract_addr } => {
+            let token_info: TokenInfoResponse =
+                querier.query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
+            Ok(token_info.decimals)
+        }
+    }
+}
+
+/// Values the asset in the reference asset by simulating a swap on the terraswap pair
+pub fn query_notional(
+    querier: &QuerierWrapper,
+    oracle: &dyn PairOracle,
+    terraswap_factory: Addr,
+    asset: &Asset,
+    notional_asset_info: AssetInfo,
+) -> StdResult<Uint128> {
+    if asset.info == notional_asset_info {
+        return Ok(asset.amount);
+    }
+
+    let pair_info: PairInfo = oracle
+        .query_pair_info(
+            querier,
+            terraswap_factory,
+            &[asset.info.clone(), notional_asset_info],
+        )
+        .map_err(|_| StdError::generic_err("there is no terraswap pair to value the offer"))?;
+
+    let simul_res: SimulationResponse =
+        oracle.simulate(querier, Addr::unchecked(pair_info.contract_addr), asset)?;
+
+    Ok(simul_res.return_amount)
+}
+
+/// Pair lookups and swap simulations used to submit and execute orders, so that they
+/// can run against a mock pair instead of a terraswap deployment
+pub trait PairOracle {
+    fn query_pair_info(
+        &self,
+        querier: &QuerierWrapper,
+        factory_addr: Addr,
+        asset_infos: &[AssetInfo; 2],
+    ) -> StdResult<PairInfo>;
+
+    fn simulate(
+        &self,
+        querier: &QuerierWrapper,
+        pair_addr: Addr,
+        offer_asset: &Asset,
+    ) -> StdResult<SimulationResponse>;
+
+    /// Resolves the pair of the assets from terraswap_factory, then from fallback_factory.
+    /// Returns the factory the pair was found in along with the pair
+    fn query_pair_info_with_fallback(
+        &self,
+        querier: &QuerierWrapper,
+        terraswap_factory: Addr,
+        fallback_factory: Option<Addr>,
+        asset_infos: &[AssetInfo; 2],
+    ) -> StdResult<(Addr, PairInfo)> {
+        if let Ok(pair_info) = self.query_pair_info(querier, terraswap_factory.clone(), asset_infos)
+        {
+            return Ok((terraswap_factory, pair_info));
+        }
+
+        fallback_factory
+            .and_then(|fallback_factory| {
+                self.query_pair_info(querier, fallback_factory.clone(), asset_infos)
+                    .ok()
+                    .map(|pair_info| (fallback_factory, pair_info))
+            })
+            .ok_or_else(|| {
+                StdError::generic_err("there is no terraswap pair for the 2 assets provided")
+            })
+    }
+}
+
+/// PairOracle querying the terraswap factory and pairs
+pub struct TerraswapOracle;
+
+impl PairOracle for TerraswapOracle {
+    fn query_pair_info(
+        &self,
+        querier: &QuerierWrapper,
+        factory_addr: Addr,
+        asset_infos: &[AssetInfo; 2],
+    ) -> StdResult<PairInfo> {
+        query_pair_info(querier, factory_addr, asset_infos)
+    }
+
+    fn simulate(
+        &self,
+        querier: &QuerierWrapper,
+        pair_addr: Addr,
+        offer_asset: &Asset,
+    ) -> StdResult<SimulationResponse> {
+        simulate(querier, pair_addr, offer_asset)
+    }
+}
+
+/// Amount of the asset held by the pair
+pub fn query_pool_reserve(
+    querier: &QuerierWrapper,
+    pair_addr: Addr,
+    asset_info: &AssetInfo,
+) -> StdResult<Uint128> {
+    let pool: PoolResponse = querier.query_wasm_smart(pair_addr, &PairQueryMsg::Pool {})?;
+
+    pool.assets
+        .iter()
+        .find(|asset| asset.info == *asset_info)
+        .map(|asset| asset.amount)
+        .ok_or_else(|| StdError::generic_err("the pair does not hold the asset"))
+}
@@ -1,10 +1,40 @@
-use cosmwasm_std::{Deps, StdResult};
+use std::cmp::Reverse;
+use std::convert::TryInto;
+
+use cosmwasm_std::{Decimal, Deps, Env, Order, StdError, StdResult, Uint128, Uint256};
+use cw20::{AllowanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use terraswap::asset::{Asset, AssetInfo, PairInfo};
+use terraswap::pair::SimulationResponse;
 
 use crate::{
-    msg::{ConfigResponse, LastOrderIdResponse, OrderBy, OrderResponse, OrdersResponse},
-    state::{read_orders, read_orders_by_user, Config, OrderInfo, CONFIG, LAST_ORDER_ID, ORDERS},
+    msg::{
+        AllowanceCheckResponse, ConfigResponse, ExecutorStatsResponse, FeeTokenInfoResponse,
+        FillResponse, FillsResponse, HasOpenOrdersResponse, LastOrderIdResponse,
+        MaxFillWithinSpreadResponse, MedianPriceResponse, OptimalFillSetResponse, OrderBy,
+        OrderCountsByPairResponse, OrderGroupResponse, OrderResponse, OrderSide, OrderSort,
+        OrderStatsResponse, OrderWithBidderContextResponse, OrdersResponse, PairStatsResponse,
+        PauseStateResponse, PreviewExecutionResponse, PreviewSubmitResponse, PriceCursor,
+        PrioritizedOrder, PrioritizedOrdersResponse, RecentResponse, TopBiddersResponse,
+        TotalEscrowedFeesResponse, TransferPreview, UnderwaterOrdersResponse,
+    },
+    querier::{query_notional, PairOracle},
+    state::{
+        assert_executable, assert_pair_assets, checked_mul_decimal, has_open_orders, page_limit,
+        read_bidder_order_totals, read_fills, read_order_counts_by_pair, read_order_range,
+        read_orders, read_orders_by_ask_asset, read_orders_by_denom, read_orders_by_pair,
+        read_orders_by_pair_side, read_orders_by_price, read_orders_by_user, Config, FillPayout,
+        OrderGroupInfo, OrderInfo, BLOCKED_ASSETS, CONFIG, ESCROWED_OFFER_BY_USER, FEES_BY_PAIR,
+        FILLS_BY_EXECUTOR, FILLS_BY_PAIR, LAST_FILL_BY_EXECUTOR, LAST_ORDER_ID, MAX_LIMIT,
+        OPEN_ASK_FEES, OPEN_ORDERS_BY_USER, ORDERS, ORDER_GROUPS, PAUSED_PAIRS, PRICE_PRECISION,
+        TOTAL_OPEN_FEES, TOTAL_OPEN_ORDERS,
+    },
 };
 
+// simulations run by query_max_fill_within_spread at most
+const MAX_FILL_SEARCH_ITERATIONS: u32 = 24;
+// orders ranked by a page of query_prioritized_orders at most
+const MAX_PRIORITIZED_ORDERS: usize = 100;
+
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config: Config = CONFIG.load(deps.storage)?;
 
@@ -23,24 +53,53 @@ pub fn query_orders(
     start_after: Option<u64>,
     limit: Option<u32>,
     order_by: Option<OrderBy>,
+    min_offer_amount: Option<Uint128>,
+    sort_by: Option<OrderSort>,
 ) -> StdResult<OrdersResponse> {
-    let orders: Vec<OrderInfo> = if let Some(bidder_addr) = bidder_addr {
-        read_orders_by_user(
-            deps.storage,
-            &deps.api.addr_validate(&bidder_addr)?,
-            start_after,
-            limit,
-            order_by,
-        )?
+    let (mut orders, total_open): (Vec<OrderInfo>, u64) = if let Some(bidder_addr) = bidder_addr {
+        let bidder_addr = deps.api.addr_validate(&bidder_addr)?;
+        (
+            read_orders_by_user(deps.storage, &bidder_addr, start_after, limit, order_by)?,
+            OPEN_ORDERS_BY_USER
+                .may_load(deps.storage, bidder_addr.as_bytes())?
+                .unwrap_or(0u64),
+        )
     } else {
-        read_orders(deps.storage, start_after, limit, order_by)?
+        (
+            read_orders(deps.storage, start_after, limit, order_by)?,
+            TOTAL_OPEN_ORDERS.load(deps.storage)?,
+        )
     };
 
+    // a full page may be followed by more orders
+    let page_size = page_limit(limit);
+    let next_start_after: Option<u64> = if orders.len() == page_size {
+        orders.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    // filtered within the page read, so the scan stays bounded by limit and
+    // the cursor continues after the last order read even if it is filtered out
+    if let Some(min_offer_amount) = min_offer_amount {
+        orders.retain(|order| order.offer_asset.amount >= min_offer_amount);
+    }
+
+    // only the page is sorted, the cursor above is still taken in id order
+    match sort_by {
+        Some(OrderSort::Price) => orders.sort_by_key(|order| order.implied_price()),
+        Some(OrderSort::Fee) => orders.sort_by_key(|order| Reverse(order.fee_amount)),
+        Some(OrderSort::Age) => orders.sort_by_key(|order| order.created_at),
+        Some(OrderSort::Id) | None => {}
+    }
+
     let resp = OrdersResponse {
         orders: orders
             .iter()
             .map(|order| order.as_res())
             .collect::<StdResult<Vec<OrderResponse>>>()?,
+        next_start_after,
+        total_open: Some(total_open),
     };
 
     Ok(resp)
@@ -51,3 +110,859 @@ pub fn query_last_order_id(deps: Deps) -> StdResult<LastOrderIdResponse> {
 
     Ok(LastOrderIdResponse { last_order_id })
 }
+
+pub fn query_order_stats(deps: Deps) -> StdResult<OrderStatsResponse> {
+    let last_order_id: u64 = LAST_ORDER_ID.load(deps.storage)?;
+    let open_count: u64 = TOTAL_OPEN_ORDERS.load(deps.storage)?;
+
+    Ok(OrderStatsResponse {
+        last_order_id,
+        total_created: last_order_id,
+        open_count,
+    })
+}
+
+pub fn query_fills(
+    deps: Deps,
+    pair_addr: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<FillsResponse> {
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    let fills: Vec<FillResponse> = read_fills(deps.storage, &pair_addr, start_after, limit)?
+        .iter()
+        .map(|fill| fill.as_res())
+        .collect::<StdResult<Vec<FillResponse>>>()?;
+
+    Ok(FillsResponse { fills })
+}
+
+pub fn query_order_group(deps: Deps, group_id: u64) -> StdResult<OrderGroupResponse> {
+    let group: OrderGroupInfo = ORDER_GROUPS.load(deps.storage, &group_id.to_be_bytes())?;
+
+    group.as_res()
+}
+
+pub fn query_orders_near_price(
+    deps: Deps,
+    pair_addr: String,
+    offer_asset_info: AssetInfo,
+    target_price: Decimal,
+    band_bps: u64,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let target_price: Uint128 = checked_mul_decimal(Uint128::new(PRICE_PRECISION), target_price)
+        .ok_or_else(|| StdError::generic_err("target_price overflows"))?;
+    let min_price = target_price.multiply_ratio(10000u64.saturating_sub(band_bps), 10000u64);
+    let max_price: Uint128 = (target_price.full_mul(10000u64.saturating_add(band_bps))
+        / Uint256::from(10000u64))
+    .try_into()
+    .map_err(|_| StdError::generic_err("target_price overflows at the top of the band"))?;
+
+    let orders: Vec<OrderInfo> = read_orders_by_price(
+        deps.storage,
+        &deps.api.addr_validate(&pair_addr)?,
+        &offer_asset_info,
+        min_price,
+        max_price,
+        limit,
+    )?;
+
+    let resp = OrdersResponse {
+        orders: orders
+            .iter()
+            .map(|order| order.as_res())
+            .collect::<StdResult<Vec<OrderResponse>>>()?,
+        next_start_after: None,
+        total_open: None,
+    };
+
+    Ok(resp)
+}
+
+pub fn query_executor_stats(deps: Deps, executor: String) -> StdResult<ExecutorStatsResponse> {
+    let executor = deps.api.addr_validate(&executor)?;
+    let fill_count = FILLS_BY_EXECUTOR
+        .may_load(deps.storage, executor.as_bytes())?
+        .unwrap_or(0u64);
+    let last_filled_order_id = LAST_FILL_BY_EXECUTOR.may_load(deps.storage, executor.as_bytes())?;
+
+    Ok(ExecutorStatsResponse {
+        executor: executor.to_string(),
+        fill_count,
+        last_filled_order_id,
+    })
+}
+
+pub fn query_underwater_orders(
+    deps: Deps,
+    oracle: &dyn PairOracle,
+    pair_addr: String,
+    min_shortfall_bps: u64,
+    start_after: Option<PriceCursor>,
+    limit: Option<u32>,
+) -> StdResult<UnderwaterOrdersResponse> {
+    if min_shortfall_bps > 10000u64 {
+        return Err(StdError::generic_err(
+            "min_shortfall_bps must not exceed 10000",
+        ));
+    }
+
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    let limit = page_limit(limit);
+
+    // every scanned order is simulated, so the scan itself is bounded by limit
+    let scanned: Vec<OrderInfo> =
+        read_orders_by_pair(deps.storage, &pair_addr, start_after.as_ref(), limit)?;
+    // the cursor is the index position itself, so it outlives the order it was taken from
+    let next_start_after: Option<PriceCursor> = if scanned.len() == limit {
+        scanned.last().map(|order| order.price_cursor())
+    } else {
+        None
+    };
+
+    let mut orders: Vec<OrderResponse> = vec![];
+    for order in scanned.iter() {
+        let max_return: Uint128 = order
+            .ask_asset
+            .amount
+            .multiply_ratio(10000u64 - min_shortfall_bps, 10000u64);
+        // orders which can't be simulated right now are skipped
+        if matches!(simulate_return(deps, oracle, order), Ok(return_amount) if return_amount < max_return)
+        {
+            orders.push(order.as_res()?);
+        }
+    }
+
+    Ok(UnderwaterOrdersResponse {
+        orders,
+        next_start_after,
+    })
+}
+
+pub fn query_pair_stats(deps: Deps, pair_addr: String) -> StdResult<PairStatsResponse> {
+    let pair_addr = deps.api.addr_validate(&pair_addr)?;
+    let fill_count = FILLS_BY_PAIR
+        .may_load(deps.storage, pair_addr.as_bytes())?
+        .unwrap_or(0u64);
+    let fees: Uint128 = FEES_BY_PAIR
+        .may_load(deps.storage, pair_addr.as_bytes())?
+        .unwrap_or_default();
+    let average_fee: Uint128 = if fill_count == 0 {
+        Uint128::zero()
+    } else {
+        fees.multiply_ratio(1u64, fill_count)
+    };
+
+    Ok(PairStatsResponse {
+        pair_addr: pair_addr.to_string(),
+        fill_count,
+        average_fee,
+    })
+}
+
+pub fn query_order_range(deps: Deps, from_id: u64, to_id: u64) -> StdResult<OrdersResponse> {
+    let orders: Vec<OrderInfo> = read_order_range(deps.storage, from_id, to_id)?;
+
+    // the range is capped at MAX_LIMIT
+    let next_start_after: Option<u64> = if orders.len() == MAX_LIMIT as usize {
+        orders.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    let resp = OrdersResponse {
+        orders: orders
+            .iter()
+            .map(|order| order.as_res())
+            .collect::<StdResult<Vec<OrderResponse>>>()?,
+        next_start_after,
+        total_open: None,
+    };
+
+    Ok(resp)
+}
+
+pub fn query_has_open_orders(deps: Deps, bidder_addr: String) -> StdResult<HasOpenOrdersResponse> {
+    let has_open_orders = has_open_orders(deps.storage, &deps.api.addr_validate(&bidder_addr)?);
+
+    Ok(HasOpenOrdersResponse { has_open_orders })
+}
+
+pub fn query_profitable_orders(
+    deps: Deps,
+    env: Env,
+    oracle: &dyn PairOracle,
+    gas_cost_in_fee_token: Uint128,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let now: u64 = env.block.time.seconds();
+
+    // every scanned order is simulated, so the scan itself is bounded by limit
+    let scanned: Vec<OrderInfo> =
+        read_orders(deps.storage, start_after, limit, Some(OrderBy::Asc))?;
+    let next_start_after: Option<u64> = if scanned.len() == page_limit(limit) {
+        scanned.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    let mut orders: Vec<OrderResponse> = vec![];
+    for order in scanned.iter().filter(|order| !order.is_expired(now)) {
+        // orders which can't be filled right now are skipped
+        if let Ok(reward) = simulate_executor_reward(deps, oracle, &config, order, now) {
+            if reward >= gas_cost_in_fee_token {
+                orders.push(order.as_res()?);
+            }
+        }
+    }
+
+    Ok(OrdersResponse {
+        orders,
+        next_start_after,
+        total_open: None,
+    })
+}
+
+pub fn query_optimal_fill_set(
+    deps: Deps,
+    env: Env,
+    oracle: &dyn PairOracle,
+    gas_per_fill: u64,
+    budget: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OptimalFillSetResponse> {
+    if gas_per_fill == 0 {
+        return Err(StdError::generic_err("gas_per_fill must be positive"));
+    }
+
+    let config: Config = CONFIG.load(deps.storage)?;
+    let now: u64 = env.block.time.seconds();
+
+    // every scanned order is simulated, so the scan itself is bounded by limit
+    let scanned: Vec<OrderInfo> =
+        read_orders(deps.storage, start_after, limit, Some(OrderBy::Asc))?;
+    let next_start_after: Option<u64> = if scanned.len() == page_limit(limit) {
+        scanned.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    let mut candidates: Vec<(u64, Uint128)> = vec![];
+    for order in scanned.iter().filter(|order| !order.is_expired(now)) {
+        // orders which can't be filled right now are skipped
+        if let Ok(reward) = simulate_executor_reward(deps, oracle, &config, order, now) {
+            candidates.push((order.order_id, reward));
+        }
+    }
+
+    // every fill costs the same gas, so the best reward per gas is the best reward
+    candidates.sort_by_key(|(order_id, reward)| (Reverse(*reward), *order_id));
+    let max_fills: usize = (budget / gas_per_fill) as usize;
+    candidates.truncate(max_fills);
+
+    let mut total_reward = Uint128::zero();
+    for (_, reward) in candidates.iter() {
+        total_reward = total_reward.checked_add(*reward)?;
+    }
+
+    Ok(OptimalFillSetResponse {
+        order_ids: candidates
+            .into_iter()
+            .map(|(order_id, _)| order_id)
+            .collect(),
+        total_reward,
+        next_start_after,
+    })
+}
+
+/// Ask asset returned by swapping the offer of the order now, net of the native tax
+fn simulate_return(deps: Deps, oracle: &dyn PairOracle, order: &OrderInfo) -> StdResult<Uint128> {
+    let offer_asset = if order.offer_asset.is_native_token() {
+        Asset {
+            amount: order.offer_asset.deduct_tax(&deps.querier)?.amount,
+            ..order.offer_asset.clone()
+        }
+    } else {
+        order.offer_asset.clone()
+    };
+
+    let pair_info: PairInfo = oracle.query_pair_info(
+        &deps.querier,
+        order.factory_addr.clone(),
+        &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+    )?;
+    let simul_res: SimulationResponse = oracle.simulate(
+        &deps.querier,
+        deps.api.addr_validate(pair_info.contract_addr.as_str())?,
+        &offer_asset,
+    )?;
+
+    Ok(simul_res.return_amount)
+}
+
+/// Fee plus the excess return of the swap valued in the fee token, as if executed now
+fn simulate_executor_reward(
+    deps: Deps,
+    oracle: &dyn PairOracle,
+    config: &Config,
+    order: &OrderInfo,
+    now: u64,
+) -> StdResult<Uint128> {
+    let excess_asset = Asset {
+        info: order.ask_asset.info.clone(),
+        amount: simulate_return(deps, oracle, order)?.checked_sub(order.ask_asset.amount)?,
+    };
+    // a fee in the ask asset is valued along with the excess
+    let fee_amount: Uint128 = order.effective_fee(now, config.fee_bump_period);
+    let (excess_asset, fee_amount) = if config.fee_denominated_in_ask {
+        (
+            Asset {
+                amount: excess_asset.amount.checked_add(fee_amount)?,
+                ..excess_asset
+            },
+            Uint128::zero(),
+        )
+    } else {
+        (excess_asset, fee_amount)
+    };
+    let excess_value: Uint128 = if excess_asset.amount.is_zero() {
+        Uint128::zero()
+    } else {
+        query_notional(
+            &deps.querier,
+            oracle,
+            config.terraswap_factory.clone(),
+            &excess_asset,
+            AssetInfo::Token {
+                contract_addr: config.fee_token.to_string(),
+            },
+        )
+        .unwrap_or_default()
+    };
+
+    Ok(fee_amount + excess_value)
+}
+
+pub fn query_max_fill_within_spread(
+    deps: Deps,
+    oracle: &dyn PairOracle,
+    order_id: u64,
+    max_spread_bps: u64,
+) -> StdResult<MaxFillWithinSpreadResponse> {
+    if max_spread_bps > 10000u64 {
+        return Err(StdError::generic_err(
+            "max_spread_bps must not exceed 10000",
+        ));
+    }
+
+    let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+
+    // same spread check as the pair: spread / (return + spread) <= max spread
+    let within_spread = |amount: Uint128| -> StdResult<bool> {
+        let simul_res: SimulationResponse = oracle.simulate(
+            &deps.querier,
+            order.pair_addr.clone(),
+            &Asset {
+                info: order.offer_asset.info.clone(),
+                amount,
+            },
+        )?;
+
+        Ok(simul_res
+            .spread_amount
+            .checked_mul(Uint128::from(10000u128))?
+            <= simul_res
+                .return_amount
+                .checked_add(simul_res.spread_amount)?
+                .checked_mul(Uint128::from(max_spread_bps))?)
+    };
+
+    if within_spread(order.offer_asset.amount)? {
+        return Ok(MaxFillWithinSpreadResponse {
+            fill_amount: order.offer_asset.amount,
+        });
+    }
+
+    // low is always within the spread and high never is
+    let mut low = Uint128::zero();
+    let mut high: Uint128 = order.offer_asset.amount;
+    for _ in 0..MAX_FILL_SEARCH_ITERATIONS {
+        let step: Uint128 = high.checked_sub(low)?;
+        if step <= Uint128::from(1u128) {
+            break;
+        }
+
+        let mid: Uint128 = low + step.multiply_ratio(1u128, 2u128);
+        if within_spread(mid)? {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(MaxFillWithinSpreadResponse { fill_amount: low })
+}
+
+pub fn query_prioritized_orders(
+    deps: Deps,
+    env: Env,
+    pair_addr: String,
+    start_after: Option<PriceCursor>,
+    limit: Option<u32>,
+) -> StdResult<PrioritizedOrdersResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let limit = page_limit(limit);
+    let now: u64 = env.block.time.seconds();
+
+    let scanned: Vec<OrderInfo> = read_orders_by_pair(
+        deps.storage,
+        &deps.api.addr_validate(&pair_addr)?,
+        start_after.as_ref(),
+        MAX_PRIORITIZED_ORDERS,
+    )?;
+    let next_start_after: Option<PriceCursor> = if scanned.len() == MAX_PRIORITIZED_ORDERS {
+        scanned.last().map(|order| order.price_cursor())
+    } else {
+        None
+    };
+
+    let mut scored_orders: Vec<(Uint128, OrderInfo)> = scanned
+        .into_iter()
+        .filter(|order| !order.is_expired(now))
+        .map(|order| Ok((order.priority_score(now, config.fee_bump_period)?, order)))
+        .collect::<StdResult<Vec<(Uint128, OrderInfo)>>>()?;
+
+    // highest score first, ties broken by the oldest order id
+    scored_orders.sort_by(|(a_score, a), (b_score, b)| {
+        b_score.cmp(a_score).then(a.order_id.cmp(&b.order_id))
+    });
+
+    let orders = scored_orders
+        .iter()
+        .take(limit)
+        .map(|(priority_score, order)| {
+            Ok(PrioritizedOrder {
+                order: order.as_res()?,
+                priority_score: *priority_score,
+            })
+        })
+        .collect::<StdResult<Vec<PrioritizedOrder>>>()?;
+
+    Ok(PrioritizedOrdersResponse {
+        orders,
+        next_start_after,
+    })
+}
+
+pub fn query_order_with_bidder_context(
+    deps: Deps,
+    order_id: u64,
+) -> StdResult<OrderWithBidderContextResponse> {
+    let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+    // both are kept up to date as orders open and close, so no order is read
+    let bidder_open_order_count: u64 = OPEN_ORDERS_BY_USER
+        .may_load(deps.storage, order.bidder_addr.as_bytes())?
+        .unwrap_or(0u64);
+    let bidder_escrowed_offer: Uint128 = ESCROWED_OFFER_BY_USER
+        .may_load(
+            deps.storage,
+            (
+                order.bidder_addr.as_bytes(),
+                order.offer_asset.info.to_string().as_bytes(),
+            ),
+        )?
+        .unwrap_or_default();
+
+    Ok(OrderWithBidderContextResponse {
+        order: order.as_res()?,
+        bidder_open_order_count,
+        bidder_escrowed_offer,
+    })
+}
+
+pub fn query_orders_by_denom(
+    deps: Deps,
+    denom: String,
+    side: OrderSide,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let orders: Vec<OrderInfo> =
+        read_orders_by_denom(deps.storage, &denom, side, start_after, limit)?;
+
+    let page_size = page_limit(limit);
+    let next_start_after: Option<u64> = if orders.len() == page_size {
+        orders.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    let resp = OrdersResponse {
+        orders: orders
+            .iter()
+            .map(|order| order.as_res())
+            .collect::<StdResult<Vec<OrderResponse>>>()?,
+        next_start_after,
+        total_open: None,
+    };
+
+    Ok(resp)
+}
+
+pub fn query_orders_by_ask_asset(
+    deps: Deps,
+    ask_asset: AssetInfo,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let orders: Vec<OrderInfo> =
+        read_orders_by_ask_asset(deps.storage, &ask_asset, start_after, limit)?;
+
+    let page_size = page_limit(limit);
+    let next_start_after: Option<u64> = if orders.len() == page_size {
+        orders.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    let resp = OrdersResponse {
+        orders: orders
+            .iter()
+            .map(|order| order.as_res())
+            .collect::<StdResult<Vec<OrderResponse>>>()?,
+        next_start_after,
+        total_open: None,
+    };
+
+    Ok(resp)
+}
+
+pub fn query_median_price(
+    deps: Deps,
+    pair_addr: String,
+    offer_asset_info: AssetInfo,
+) -> StdResult<MedianPriceResponse> {
+    // already sorted by implied price
+    let prices: Vec<Uint128> = read_orders_by_pair_side(
+        deps.storage,
+        &deps.api.addr_validate(&pair_addr)?,
+        &offer_asset_info,
+    )?
+    .iter()
+    .map(|order| order.implied_price())
+    .collect();
+
+    let mid = prices.len() / 2;
+    let median_price: Option<Uint128> = if prices.is_empty() {
+        None
+    } else if prices.len() % 2 == 1 {
+        Some(prices[mid])
+    } else {
+        Some(
+            prices[mid - 1]
+                .checked_add(prices[mid])?
+                .multiply_ratio(1u128, 2u128),
+        )
+    };
+
+    Ok(MedianPriceResponse {
+        median_price: median_price.map(|price| Decimal::from_ratio(price, PRICE_PRECISION)),
+    })
+}
+
+pub fn query_orphaned_orders(
+    deps: Deps,
+    oracle: &dyn PairOracle,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<OrdersResponse> {
+    let orders: Vec<OrderInfo> = read_orders(deps.storage, start_after, limit, Some(OrderBy::Asc))?;
+
+    // the scan continues after the page, orphaned or not
+    let next_start_after: Option<u64> = if orders.len() == page_limit(limit) {
+        orders.last().map(|order| order.order_id)
+    } else {
+        None
+    };
+
+    let orphaned_orders = orders
+        .iter()
+        .filter(|order| {
+            oracle
+                .query_pair_info(
+                    &deps.querier,
+                    order.factory_addr.clone(),
+                    &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+                )
+                .is_err()
+        })
+        .map(|order| order.as_res())
+        .collect::<StdResult<Vec<OrderResponse>>>()?;
+
+    Ok(OrdersResponse {
+        orders: orphaned_orders,
+        next_start_after,
+        total_open: None,
+    })
+}
+
+pub fn query_recent(deps: Deps, count: u32) -> StdResult<RecentResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let orders: Vec<OrderInfo> = read_orders(deps.storage, None, Some(count), Some(OrderBy::Desc))?;
+
+    Ok(RecentResponse {
+        config: config.as_res()?,
+        orders: orders
+            .iter()
+            .map(|order| order.as_res())
+            .collect::<StdResult<Vec<OrderResponse>>>()?,
+    })
+}
+
+pub fn query_total_escrowed_fees(deps: Deps) -> StdResult<TotalEscrowedFeesResponse> {
+    let total_escrowed_fees: Uint128 = TOTAL_OPEN_FEES.load(deps.storage)?;
+    let ask_escrowed_fees: Vec<Asset> = OPEN_ASK_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, open_fees)| open_fees))
+        .collect::<StdResult<Vec<Asset>>>()?;
+
+    Ok(TotalEscrowedFeesResponse {
+        total_escrowed_fees,
+        ask_escrowed_fees,
+    })
+}
+
+pub fn query_fee_token_info(deps: Deps) -> StdResult<FeeTokenInfoResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    // the fee token is always a cw20 token
+    let token_info: TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(config.fee_token.to_string(), &Cw20QueryMsg::TokenInfo {})?;
+
+    Ok(FeeTokenInfoResponse {
+        fee_token: config.fee_token.to_string(),
+        name: token_info.name,
+        symbol: token_info.symbol,
+        decimals: token_info.decimals,
+    })
+}
+
+pub fn query_pause_state(
+    deps: Deps,
+    pair_addr: Option<String>,
+    offer_asset_info: Option<AssetInfo>,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<PauseStateResponse> {
+    let pair_paused = match pair_addr {
+        Some(pair_addr) => {
+            PAUSED_PAIRS.has(deps.storage, deps.api.addr_validate(&pair_addr)?.as_bytes())
+        }
+        None => false,
+    };
+    let is_blocked = |asset_info: Option<AssetInfo>| matches!(asset_info, Some(asset_info) if BLOCKED_ASSETS.has(deps.storage, asset_info.to_string()));
+
+    Ok(PauseStateResponse {
+        pair_paused,
+        blocked_offer: is_blocked(offer_asset_info),
+        blocked_ask: is_blocked(ask_asset_info),
+    })
+}
+
+pub fn query_order_counts_by_pair(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OrderCountsByPairResponse> {
+    let start_after = start_after
+        .map(|pair_addr| deps.api.addr_validate(&pair_addr))
+        .transpose()?;
+    let order_counts = read_order_counts_by_pair(deps.storage, start_after, limit)?;
+
+    Ok(OrderCountsByPairResponse { order_counts })
+}
+
+pub fn query_top_bidders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TopBiddersResponse> {
+    let start_after = start_after
+        .map(|bidder_addr| deps.api.addr_validate(&bidder_addr))
+        .transpose()?;
+    let bidders = read_bidder_order_totals(deps.storage, start_after, limit)?;
+
+    Ok(TopBiddersResponse { bidders })
+}
+
+pub fn query_preview_submit(
+    deps: Deps,
+    sender: String,
+    offer_asset: Asset,
+    ask_asset_info: AssetInfo,
+    fee_amount: Uint128,
+    fee_bond: Option<Uint128>,
+    rebuy_fee: Option<Uint128>,
+) -> StdResult<PreviewSubmitResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let sender = deps.api.addr_validate(&sender)?;
+
+    let fee_asset = Asset {
+        info: config.fee_asset_info(&ask_asset_info),
+        amount: escrowed_fee_amount(fee_amount, fee_bond, rebuy_fee)?,
+    };
+
+    // an offer in the fee asset is transferred along with the fee
+    let assets: Vec<Asset> = if offer_asset.info == fee_asset.info {
+        vec![Asset {
+            amount: offer_asset.amount.checked_add(fee_asset.amount)?,
+            ..fee_asset
+        }]
+    } else {
+        vec![offer_asset, fee_asset]
+    };
+
+    let transfers: Vec<TransferPreview> = assets
+        .into_iter()
+        .map(|asset| TransferPreview {
+            from: sender.to_string(),
+            allowance_required: !asset.is_native_token(),
+            asset,
+        })
+        .collect();
+
+    Ok(PreviewSubmitResponse { transfers })
+}
+
+pub fn query_preview_execution(
+    deps: Deps,
+    env: Env,
+    oracle: &dyn PairOracle,
+    order_id: u64,
+    executor: String,
+) -> StdResult<PreviewExecutionResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let order: OrderInfo = ORDERS.load(deps.storage, &order_id.to_be_bytes())?;
+    let executor = deps.api.addr_validate(&executor)?;
+    assert_executable(
+        deps.storage,
+        &config,
+        &order,
+        &executor,
+        env.block.time.seconds(),
+    )?;
+
+    // simulate the swap execute_order would send
+    let offer_asset = if order.offer_asset.is_native_token() {
+        Asset {
+            amount: order.offer_asset.deduct_tax(&deps.querier)?.amount,
+            ..order.offer_asset.clone()
+        }
+    } else {
+        order.offer_asset.clone()
+    };
+    let pair_info: PairInfo = oracle
+        .query_pair_info(
+            &deps.querier,
+            order.factory_addr.clone(),
+            &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+        )
+        .map_err(|_| {
+            StdError::generic_err("there is no terraswap pair for the 2 assets provided")
+        })?;
+    assert_pair_assets(
+        &pair_info,
+        &[order.offer_asset.info.clone(), order.ask_asset.info.clone()],
+    )?;
+    let simul_res: SimulationResponse = oracle.simulate(
+        &deps.querier,
+        deps.api.addr_validate(pair_info.contract_addr.as_str())?,
+        &offer_asset,
+    )?;
+
+    let payout: FillPayout =
+        order.fill_payout(&config, simul_res.return_amount, env.block.time.seconds())?;
+
+    Ok(PreviewExecutionResponse {
+        bidder_receives: Asset {
+            info: order.ask_asset.info.clone(),
+            amount: payout.bidder_amount,
+        },
+        executor_fee: Asset {
+            info: config.fee_asset_info(&order.ask_asset.info),
+            amount: payout.executor_fee_amount,
+        },
+        executor_excess: Asset {
+            info: order.ask_asset.info,
+            amount: payout.excess_amount,
+        },
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn query_allowance_check(
+    deps: Deps,
+    env: Env,
+    owner: String,
+    offer_asset: Asset,
+    ask_asset_info: AssetInfo,
+    fee_amount: Uint128,
+    fee_bond: Option<Uint128>,
+    rebuy_fee: Option<Uint128>,
+) -> StdResult<AllowanceCheckResponse> {
+    let config: Config = CONFIG.load(deps.storage)?;
+    let owner = deps.api.addr_validate(&owner)?;
+
+    let contract_addr: String = match offer_asset.info.clone() {
+        AssetInfo::NativeToken { .. } => {
+            return Ok(AllowanceCheckResponse {
+                sufficient: true,
+                current: Uint128::zero(),
+                required: Uint128::zero(),
+            })
+        }
+        AssetInfo::Token { contract_addr } => contract_addr,
+    };
+
+    // an offer in the fee asset is transferred along with the fee
+    let required: Uint128 = if offer_asset.info == config.fee_asset_info(&ask_asset_info) {
+        offer_asset
+            .amount
+            .checked_add(escrowed_fee_amount(fee_amount, fee_bond, rebuy_fee)?)?
+    } else {
+        offer_asset.amount
+    };
+
+    let allowance: AllowanceResponse = deps.querier.query_wasm_smart(
+        contract_addr,
+        &Cw20QueryMsg::Allowance {
+            owner: owner.to_string(),
+            spender: env.contract.address.to_string(),
+        },
+    )?;
+    let current: Uint128 = if allowance.expires.is_expired(&env.block) {
+        Uint128::zero()
+    } else {
+        allowance.allowance
+    };
+
+    Ok(AllowanceCheckResponse {
+        sufficient: current >= required,
+        current,
+        required,
+    })
+}
+
+// fee, fee bond and rebuy fee escrowed on submit, as OrderInfo::escrowed_fee
+fn escrowed_fee_amount(
+    fee_amount: Uint128,
+    fee_bond: Option<Uint128>,
+    rebuy_fee: Option<Uint128>,
+) -> StdResult<Uint128> {
+    Ok(fee_amount
+        .checked_add(fee_bond.unwrap_or_default())?
+        .checked_add(rebuy_fee.unwrap_or_default())?)
+}
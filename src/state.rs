@@ -1,30 +1,187 @@
+use std::convert::TryInto;
+
 use cw_storage_plus::{Bound, Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use cosmwasm_std::{Addr, Order, StdResult, Storage, Uint128};
-use terraswap::asset::Asset;
+use cosmwasm_std::{
+    Addr, Decimal, Fraction, Order, StdError, StdResult, Storage, Uint128, Uint256,
+};
+use terraswap::asset::{Asset, AssetInfo, PairInfo};
 
-use crate::msg::{ConfigResponse, OrderBy, OrderResponse};
+use crate::msg::{
+    ConfigResponse, FillHook, FillResponse, OrderBy, OrderGrid, OrderGroupResponse, OrderResponse,
+    OrderSide, PriceCursor,
+};
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const LAST_ORDER_ID: Item<u64> = Item::new("last_order_id");
 pub const ORDERS: Map<&[u8], OrderInfo> = Map::new("orders");
 pub const ORDERS_BY_USER: Map<(&[u8], &[u8]), bool> = Map::new("orders_by_user");
+// (bidder_addr, pair_addr, order_id)
+#[allow(clippy::type_complexity)]
+pub const ORDERS_BY_USER_PAIR: Map<(&[u8], &[u8], &[u8]), bool> = Map::new("orders_by_user_pair");
+pub const LAST_ORDER_GROUP_ID: Item<u64> = Item::new("last_order_group_id");
+pub const ORDER_GROUPS: Map<&[u8], OrderGroupInfo> = Map::new("order_groups");
+pub const LAST_HEARTBEAT: Map<&[u8], u64> = Map::new("last_heartbeat");
+// (pair_addr, offer_asset_info string, price_key) where the price key is the implied price
+// followed by the order id, so each side of the pair is sorted by price on its own
+#[allow(clippy::type_complexity)]
+pub const ORDERS_BY_PRICE: Map<(&[u8], &[u8], &[u8]), bool> = Map::new("orders_by_price");
+// (ask_asset_info string, order_id), the string is the full denom or the token contract address
+pub const ASK_ASSET_INDEX: Map<(&[u8], &[u8]), bool> = Map::new("ask_asset_index");
+// (offer_asset_info string, order_id)
+pub const OFFER_ASSET_INDEX: Map<(&[u8], &[u8]), bool> = Map::new("offer_asset_index");
+// number of orders ever created per bidder_addr, open or not
+pub const BIDDER_ORDER_TOTALS: Map<&[u8], u64> = Map::new("bidder_order_totals");
+// number of open orders per pair_addr, pairs without orders are removed
+pub const PAIRS_WITH_ORDERS: Map<&[u8], u64> = Map::new("pairs_with_orders");
+// keyed by the asset info string (denom or token contract address)
+pub const LAST_FILL_BY_EXECUTOR: Map<&[u8], u64> = Map::new("last_fill_by_executor");
+pub const FILLS_BY_EXECUTOR: Map<&[u8], u64> = Map::new("fills_by_executor");
+// fills and executor fees paid, by the pair_addr of the filled orders
+pub const FILLS_BY_PAIR: Map<&[u8], u64> = Map::new("fills_by_pair");
+pub const FEES_BY_PAIR: Map<&[u8], Uint128> = Map::new("fees_by_pair");
+// (executor, epoch) order fees earned by the executor in the epoch
+pub const FEE_EARNED_EPOCH: Map<(&[u8], &[u8]), Uint128> = Map::new("fee_earned_epoch");
+// order being executed, kept until the reply of its swap
+pub const PENDING_EXECUTION: Item<PendingExecution> = Item::new("pending_execution");
+pub const BLOCKED_ASSETS: Map<String, bool> = Map::new("blocked_assets");
+pub const PAUSED_PAIRS: Map<&[u8], bool> = Map::new("paused_pairs");
+pub const MIN_FEE_BY_PAIR: Map<&[u8], Uint128> = Map::new("min_fee_by_pair");
+// fee tokens rebated to each bidder_addr, until claimed
+pub const REBATES: Map<&[u8], Uint128> = Map::new("rebates");
+// fee tokens deposited by the owner to pay executor bounties
+pub const BOUNTY_POOL: Item<Uint128> = Item::new("bounty_pool");
+// incentive tokens deposited by the owner to pay executor incentives
+pub const INCENTIVE_POOL: Item<Uint128> = Item::new("incentive_pool");
+// sum of the escrowed fees (fee, fee bond and rebuy fee) over the open orders
+pub const TOTAL_OPEN_FEES: Item<Uint128> = Item::new("total_open_fees");
+// same sum by ask asset info string when fees are denominated in the ask asset,
+// TOTAL_OPEN_FEES then stays zero
+pub const OPEN_ASK_FEES: Map<&[u8], Asset> = Map::new("open_ask_fees");
+// number of open orders
+pub const TOTAL_OPEN_ORDERS: Item<u64> = Item::new("total_open_orders");
+// number of open orders per bidder_addr
+pub const OPEN_ORDERS_BY_USER: Map<&[u8], u64> = Map::new("open_orders_by_user");
+// (bidder_addr, offer_asset_info string) offer escrowed over the open orders of the bidder,
+// the escrow of a group is counted once
+pub const ESCROWED_OFFER_BY_USER: Map<(&[u8], &[u8]), Uint128> = Map::new("escrowed_offer_by_user");
+// sha256 of the payloads of the signed orders submitted, a signature is accepted once
+pub const SIGNED_ORDER_DIGESTS: Map<&[u8], bool> = Map::new("signed_order_digests");
+// (pair_addr, order_id) -> filled order, kept when retain_fills is set
+pub const FILLS: Map<(&[u8], &[u8]), FillInfo> = Map::new("fills");
+// native refund dust withheld per denom, until swept by the owner
+pub const ACCRUED_NATIVE_DUST: Map<&[u8], Uint128> = Map::new("accrued_native_dust");
+
+// implied prices are stored with the same precision as Decimal
+pub const PRICE_PRECISION: u128 = 1_000_000_000_000_000_000u128;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
+    pub owner: Addr,
     pub fee_token: Addr,
     pub min_fee_amount: Uint128,
     pub terraswap_factory: Addr,
+    /// share of the order fee paid to whoever cleans up an expired order
+    pub cleanup_reward_bps: u64,
+    /// max spread accepted by the pair when an order is executed
+    pub max_spread: Decimal,
+    /// seconds over which the fee bond of an order is released to the executor
+    pub fee_bump_period: u64,
+    /// reference asset in which the order notional is measured
+    pub notional_asset_info: Option<AssetInfo>,
+    /// min value of an offer, denominated in notional_asset_info
+    pub min_notional: Uint128,
+    /// extra fee token paid to the executor per fill while the bounty pool lasts
+    pub bounty_per_fill: Uint128,
+    /// lifetime given to orders submitted without expires_at, None for no expiry
+    pub default_order_ttl_seconds: Option<u64>,
+    /// whether orders may pay their fee as a share of the offer asset
+    pub allow_offer_fee: bool,
+    /// excess and bond refunds below it are folded into the larger payout instead of sent
+    pub dust_threshold: Uint128,
+    /// raises the min fee of orders which are large relative to the pool
+    pub dynamic_fee: bool,
+    /// share of the executor fee sent to the treasury
+    pub protocol_fee_bps: u64,
+    pub treasury: Option<Addr>,
+    /// native amount withheld from each native offer refund, see ACCRUED_NATIVE_DUST
+    pub native_refund_dust: Uint128,
+    /// seconds an order must stay open before its bidder can cancel it, unless expired
+    pub min_order_lifetime_seconds: u64,
+    /// max order fee an executor can earn per epoch of epoch_seconds, None for no cap
+    pub max_fee_per_epoch: Option<Uint128>,
+    pub epoch_seconds: u64,
+    /// fees are escrowed and paid in the ask asset of the order instead of the fee token.
+    /// The bounty pool stays in the fee token, the open fees are summed in OPEN_ASK_FEES
+    pub fee_denominated_in_ask: bool,
+    /// share of the executor fee credited to the bidder in REBATES
+    pub bidder_rebate_bps: u64,
+    /// orders older than this can no longer be executed, None for no limit
+    pub max_order_age_seconds: Option<u64>,
+    /// factory tried when terraswap_factory has no pair for the order assets
+    pub fallback_factory: Option<Addr>,
+    /// token paid to the executor per fill while the incentive pool lasts, None to disable
+    pub incentive_token: Option<Addr>,
+    pub incentive_per_fill: Uint128,
+    /// keeps filled orders in FILLS instead of dropping them
+    pub retain_fills: bool,
 }
 
 impl Config {
+    /// asset in which the fee of an order asking ask_asset_info is escrowed and paid
+    pub fn fee_asset_info(&self, ask_asset_info: &AssetInfo) -> AssetInfo {
+        if self.fee_denominated_in_ask {
+            ask_asset_info.clone()
+        } else {
+            AssetInfo::Token {
+                contract_addr: self.fee_token.to_string(),
+            }
+        }
+    }
+
+    /// epoch of the executor fee caps at the given time
+    pub fn epoch(&self, now: u64) -> u64 {
+        now.checked_div(self.epoch_seconds).unwrap_or(0u64)
+    }
+
     pub fn as_res(&self) -> StdResult<ConfigResponse> {
         let res = ConfigResponse {
+            owner: self.owner.to_string(),
             fee_token: self.fee_token.to_string(),
             min_fee_amount: self.min_fee_amount,
             terraswap_factory: self.terraswap_factory.to_string(),
+            cleanup_reward_bps: self.cleanup_reward_bps,
+            max_spread: self.max_spread,
+            fee_bump_period: self.fee_bump_period,
+            notional_asset_info: self.notional_asset_info.clone(),
+            min_notional: self.min_notional,
+            bounty_per_fill: self.bounty_per_fill,
+            default_order_ttl_seconds: self.default_order_ttl_seconds,
+            allow_offer_fee: self.allow_offer_fee,
+            dust_threshold: self.dust_threshold,
+            dynamic_fee: self.dynamic_fee,
+            protocol_fee_bps: self.protocol_fee_bps,
+            treasury: self.treasury.as_ref().map(|treasury| treasury.to_string()),
+            native_refund_dust: self.native_refund_dust,
+            min_order_lifetime_seconds: self.min_order_lifetime_seconds,
+            max_fee_per_epoch: self.max_fee_per_epoch,
+            epoch_seconds: self.epoch_seconds,
+            fee_denominated_in_ask: self.fee_denominated_in_ask,
+            bidder_rebate_bps: self.bidder_rebate_bps,
+            max_order_age_seconds: self.max_order_age_seconds,
+            fallback_factory: self
+                .fallback_factory
+                .as_ref()
+                .map(|fallback_factory| fallback_factory.to_string()),
+            incentive_token: self
+                .incentive_token
+                .as_ref()
+                .map(|incentive_token| incentive_token.to_string()),
+            incentive_per_fill: self.incentive_per_fill,
+            retain_fills: self.retain_fills,
         };
         Ok(res)
     }
@@ -34,10 +191,44 @@ impl Config {
 pub struct OrderInfo {
     pub order_id: u64,
     pub bidder_addr: Addr,
+    /// factory the pair was resolved from, the pair is resolved again from it on execution
+    pub factory_addr: Addr,
     pub pair_addr: Addr,
     pub offer_asset: Asset,
     pub ask_asset: Asset,
+    pub offer_decimals: u8,
+    pub ask_decimals: u8,
     pub fee_amount: Uint128,
+    /// extra fee escrowed by the bidder, released to the executor as the order ages
+    pub fee_bond: Uint128,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub group_id: Option<u64>,
+    pub order_hash: String,
+    pub grid: Option<OrderGrid>,
+    /// fee escrowed in the offer asset on top of offer_asset, paid to the executor
+    pub offer_fee: Uint128,
+    /// releases the fee bond over this period instead of fee_bump_period
+    pub ramp_seconds: Option<u64>,
+    pub fill_hook: Option<FillHook>,
+    /// min ask per unit of offer set by the bidder, can only be raised
+    pub reserve_price: Option<Decimal>,
+    /// max spread of the swap filling the order, config max_spread when None
+    pub max_spread: Option<Decimal>,
+}
+
+/// Amounts paid out when an order is filled, in the ask asset for the bidder and the
+/// excess, in the fee asset for the rest
+pub struct FillPayout {
+    pub bidder_amount: Uint128,
+    pub excess_amount: Uint128,
+    /// fee paid out of the order, split into the executor fee, protocol fee and rebate
+    pub fee_amount: Uint128,
+    pub executor_fee_amount: Uint128,
+    pub protocol_fee_amount: Uint128,
+    pub rebate_amount: Uint128,
+    /// unreleased fee bond refunded to the bidder
+    pub refund_bond_amount: Uint128,
 }
 
 impl OrderInfo {
@@ -45,42 +236,645 @@ impl OrderInfo {
         let res = OrderResponse {
             order_id: self.order_id,
             bidder_addr: self.bidder_addr.to_string(),
+            factory_addr: self.factory_addr.to_string(),
             pair_addr: self.pair_addr.to_string(),
             offer_asset: self.offer_asset.clone(),
             ask_asset: self.ask_asset.clone(),
             fee_amount: self.fee_amount,
+            fee_bond: self.fee_bond,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            group_id: self.group_id,
+            order_hash: self.order_hash.clone(),
+            grid: self.grid.clone(),
+            offer_fee: self.offer_fee,
+            ramp_seconds: self.ramp_seconds,
+            fill_hook: self.fill_hook.clone(),
+            reserve_price: self.reserve_price,
+            max_spread: self.max_spread,
         };
         Ok(res)
     }
+
+    /// hex encoded sha256 of "{bidder_addr}|{offer_asset}|{ask_asset}|{fee_amount}|{created_at}|{order_id}",
+    /// assets formatted as "{amount}{denom or contract_addr}"
+    pub fn compute_hash(&self) -> String {
+        let preimage = format!(
+            "{}|{}|{}|{}|{}|{}",
+            self.bidder_addr,
+            self.offer_asset,
+            self.ask_asset,
+            self.fee_amount,
+            self.created_at,
+            self.order_id
+        );
+
+        hex::encode(Sha256::digest(preimage.as_bytes()))
+    }
+
+    /// Fee paid to the executor at the given time: the fee amount plus the share
+    /// of the fee bond released linearly over fee_bump_period (or ramp_seconds) since creation
+    pub fn effective_fee(&self, now: u64, fee_bump_period: u64) -> Uint128 {
+        let fee_bump_period: u64 = self.ramp_seconds.unwrap_or(fee_bump_period);
+        let age: u64 = now.saturating_sub(self.created_at);
+        let released_bond: Uint128 = if age >= fee_bump_period {
+            self.fee_bond
+        } else {
+            self.fee_bond.multiply_ratio(age, fee_bump_period)
+        };
+
+        self.fee_amount + released_bond
+    }
+
+    /// Splits the return of a filled order between bidder, executor, treasury and rebate.
+    /// Shared by the settlement of a fill and PreviewExecution so both pay out the same
+    pub fn fill_payout(
+        &self,
+        config: &Config,
+        return_amount: Uint128,
+        now: u64,
+    ) -> StdResult<FillPayout> {
+        // excess below the dust threshold goes to the bidder
+        let mut excess_amount: Uint128 = return_amount
+            .checked_sub(self.ask_asset.amount)
+            .map_err(|_| StdError::generic_err("insufficient return amount"))?;
+        let mut bidder_amount: Uint128 = self.ask_asset.amount;
+        if excess_amount < config.dust_threshold {
+            bidder_amount += excess_amount;
+            excess_amount = Uint128::zero();
+        }
+
+        // the fee includes the share of the fee bond released so far
+        // and the unreleased bond if it is dust
+        let mut fee_amount: Uint128 = self.effective_fee(now, config.fee_bump_period);
+        let mut refund_bond_amount: Uint128 =
+            (self.fee_amount + self.fee_bond).checked_sub(fee_amount)?;
+        if refund_bond_amount < config.dust_threshold {
+            fee_amount += refund_bond_amount;
+            refund_bond_amount = Uint128::zero();
+        }
+
+        let protocol_fee_amount: Uint128 = if config.treasury.is_some() {
+            fee_amount.multiply_ratio(config.protocol_fee_bps, 10000u64)
+        } else {
+            Uint128::zero()
+        };
+        let rebate_amount: Uint128 = fee_amount.multiply_ratio(config.bidder_rebate_bps, 10000u64);
+
+        Ok(FillPayout {
+            bidder_amount,
+            excess_amount,
+            executor_fee_amount: fee_amount
+                .checked_sub(protocol_fee_amount)?
+                .checked_sub(rebate_amount)?,
+            fee_amount,
+            protocol_fee_amount,
+            rebate_amount,
+            refund_bond_amount,
+        })
+    }
+
+    /// priority_score = effective_fee * (1 + age in seconds), so older and better paying
+    /// orders rank first
+    pub fn priority_score(&self, now: u64, fee_bump_period: u64) -> StdResult<Uint128> {
+        let age: u64 = now.saturating_sub(self.created_at);
+
+        Ok(self
+            .effective_fee(now, fee_bump_period)
+            .checked_mul(Uint128::from(age + 1u64))?)
+    }
+
+    /// offer asset held for the order, including the offer fee
+    pub fn escrowed_offer(&self) -> Asset {
+        Asset {
+            info: self.offer_asset.info.clone(),
+            amount: self.offer_asset.amount + self.offer_fee,
+        }
+    }
+
+    /// fee tokens held for the order: fee, fee bond and the fee of the grid order
+    pub fn escrowed_fee(&self) -> StdResult<Uint128> {
+        let rebuy_fee: Uint128 = self
+            .grid
+            .as_ref()
+            .map(|grid| grid.rebuy_fee)
+            .unwrap_or_default();
+
+        self.fee_amount
+            .checked_add(self.fee_bond)
+            .and_then(|fee| fee.checked_add(rebuy_fee))
+            .map_err(|_| StdError::generic_err("escrowed fee amount overflows"))
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+
+    /// Implied price of the order (ask per offer, in whole tokens) scaled by PRICE_PRECISION
+    /// The amounts are normalized by the asset decimals so prices are comparable across tokens
+    pub fn implied_price(&self) -> Uint128 {
+        let scale: u128 = if self.offer_decimals >= self.ask_decimals {
+            10u128
+                .checked_pow((self.offer_decimals - self.ask_decimals) as u32)
+                .and_then(|factor| PRICE_PRECISION.checked_mul(factor))
+                .unwrap_or(u128::MAX)
+        } else {
+            10u128
+                .checked_pow((self.ask_decimals - self.offer_decimals) as u32)
+                .map_or(0u128, |factor| PRICE_PRECISION / factor)
+        };
+
+        Uint128::new(scale).multiply_ratio(self.ask_asset.amount, self.offer_asset.amount)
+    }
+
+    /// Position of the order in the price index of its pair
+    pub fn price_cursor(&self) -> PriceCursor {
+        PriceCursor {
+            offer_asset_info: self.offer_asset.info.clone(),
+            price: self.implied_price(),
+            order_id: self.order_id,
+        }
+    }
+
+    // big-endian price followed by the order id, so the index iterates by price
+    fn price_key(&self) -> Vec<u8> {
+        let mut key = self.implied_price().u128().to_be_bytes().to_vec();
+        key.extend_from_slice(&self.order_id.to_be_bytes());
+        key
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingExecution {
+    pub order_id: u64,
+    pub executor: Addr,
+    /// receives the fee and excess of the executor
+    pub recipient: Addr,
+    /// balance of the ask asset held by the contract before the swap
+    pub ask_balance_before: Uint128,
+    /// return the executor expects, given in place of the on-chain simulation
+    pub min_return: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OrderGroupInfo {
+    pub group_id: u64,
+    pub bidder_addr: Addr,
+    pub pair_addr: Addr,
+    /// remaining shared escrow, drawn down as the group orders are filled
+    pub escrow_asset: Asset,
+    pub order_ids: Vec<u64>,
+}
+
+impl OrderGroupInfo {
+    pub fn as_res(&self) -> StdResult<OrderGroupResponse> {
+        let res = OrderGroupResponse {
+            group_id: self.group_id,
+            bidder_addr: self.bidder_addr.to_string(),
+            pair_addr: self.pair_addr.to_string(),
+            escrow_asset: self.escrow_asset.clone(),
+            order_ids: self.order_ids.clone(),
+        };
+        Ok(res)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FillInfo {
+    pub order: OrderInfo,
+    /// ask asset returned by the swap, before the excess is split off
+    pub filled_return: Uint128,
+    pub executor: Addr,
+    pub filled_at: u64,
+}
+
+impl FillInfo {
+    pub fn as_res(&self) -> StdResult<FillResponse> {
+        let res = FillResponse {
+            order: self.order.as_res()?,
+            filled_return: self.filled_return,
+            executor: self.executor.to_string(),
+            filled_at: self.filled_at,
+        };
+        Ok(res)
+    }
+}
+
+// config and orders as stored by the first version of the contract, read by migrate only
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+pub const LEGACY_ORDERS: Map<&[u8], LegacyOrderInfo> = Map::new("orders");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub fee_token: Addr,
+    pub min_fee_amount: Uint128,
+    pub terraswap_factory: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyOrderInfo {
+    pub order_id: u64,
+    pub bidder_addr: Addr,
+    pub pair_addr: Addr,
+    pub offer_asset: Asset,
+    pub ask_asset: Asset,
+    pub fee_amount: Uint128,
 }
 
 pub fn store_new_order(storage: &mut dyn Storage, order: &mut OrderInfo) -> StdResult<()> {
     let new_id: u64 = LAST_ORDER_ID.load(storage)? + 1u64;
     order.order_id = new_id;
+    order.order_hash = order.compute_hash();
 
-    ORDERS.save(storage, &new_id.to_be_bytes(), order)?;
+    store_order(storage, order)?;
+    LAST_ORDER_ID.save(storage, &new_id)?;
+    BIDDER_ORDER_TOTALS.update(
+        storage,
+        order.bidder_addr.as_bytes(),
+        |total| -> StdResult<_> { Ok(total.unwrap_or(0u64) + 1u64) },
+    )?;
+
+    Ok(())
+}
+
+/// Saves the order with its id as is and adds it to every index
+pub fn store_order(storage: &mut dyn Storage, order: &OrderInfo) -> StdResult<()> {
+    ORDERS.save(storage, &order.order_id.to_be_bytes(), order)?;
     ORDERS_BY_USER.save(
         storage,
-        (order.bidder_addr.as_bytes(), &new_id.to_be_bytes()),
+        (order.bidder_addr.as_bytes(), &order.order_id.to_be_bytes()),
         &true,
     )?;
-    LAST_ORDER_ID.save(storage, &new_id)?;
+    ORDERS_BY_USER_PAIR.save(
+        storage,
+        (
+            order.bidder_addr.as_bytes(),
+            order.pair_addr.as_bytes(),
+            &order.order_id.to_be_bytes(),
+        ),
+        &true,
+    )?;
+    ORDERS_BY_PRICE.save(
+        storage,
+        (
+            order.pair_addr.as_bytes(),
+            order.offer_asset.info.to_string().as_bytes(),
+            &order.price_key(),
+        ),
+        &true,
+    )?;
+    ASK_ASSET_INDEX.save(
+        storage,
+        (
+            order.ask_asset.info.to_string().as_bytes(),
+            &order.order_id.to_be_bytes(),
+        ),
+        &true,
+    )?;
+    OFFER_ASSET_INDEX.save(
+        storage,
+        (
+            order.offer_asset.info.to_string().as_bytes(),
+            &order.order_id.to_be_bytes(),
+        ),
+        &true,
+    )?;
+    PAIRS_WITH_ORDERS.update(
+        storage,
+        order.pair_addr.as_bytes(),
+        |count| -> StdResult<_> { Ok(count.unwrap_or(0u64) + 1u64) },
+    )?;
+    increase_open_fees(storage, order, order.escrowed_fee()?)?;
+    if order.group_id.is_none() {
+        increase_escrowed_offer(storage, &order.bidder_addr, &order.escrowed_offer())?;
+    }
+    TOTAL_OPEN_ORDERS.update(storage, |count| -> StdResult<_> { Ok(count + 1u64) })?;
+    OPEN_ORDERS_BY_USER.update(
+        storage,
+        order.bidder_addr.as_bytes(),
+        |count| -> StdResult<_> { Ok(count.unwrap_or(0u64) + 1u64) },
+    )?;
 
     Ok(())
 }
 
-pub fn remove_order(storage: &mut dyn Storage, order: &OrderInfo) {
+pub fn remove_order(storage: &mut dyn Storage, order: &OrderInfo) -> StdResult<()> {
     ORDERS.remove(storage, &order.order_id.to_be_bytes());
     ORDERS_BY_USER.remove(
         storage,
         (order.bidder_addr.as_bytes(), &order.order_id.to_be_bytes()),
     );
+    ORDERS_BY_USER_PAIR.remove(
+        storage,
+        (
+            order.bidder_addr.as_bytes(),
+            order.pair_addr.as_bytes(),
+            &order.order_id.to_be_bytes(),
+        ),
+    );
+    ORDERS_BY_PRICE.remove(
+        storage,
+        (
+            order.pair_addr.as_bytes(),
+            order.offer_asset.info.to_string().as_bytes(),
+            &order.price_key(),
+        ),
+    );
+    ASK_ASSET_INDEX.remove(
+        storage,
+        (
+            order.ask_asset.info.to_string().as_bytes(),
+            &order.order_id.to_be_bytes(),
+        ),
+    );
+    OFFER_ASSET_INDEX.remove(
+        storage,
+        (
+            order.offer_asset.info.to_string().as_bytes(),
+            &order.order_id.to_be_bytes(),
+        ),
+    );
+
+    let count: u64 = PAIRS_WITH_ORDERS
+        .may_load(storage, order.pair_addr.as_bytes())?
+        .unwrap_or(0u64)
+        .saturating_sub(1u64);
+    if count == 0 {
+        PAIRS_WITH_ORDERS.remove(storage, order.pair_addr.as_bytes());
+    } else {
+        PAIRS_WITH_ORDERS.save(storage, order.pair_addr.as_bytes(), &count)?;
+    }
+    decrease_open_fees(storage, order, order.escrowed_fee()?)?;
+    if order.group_id.is_none() {
+        decrease_escrowed_offer(storage, &order.bidder_addr, &order.escrowed_offer())?;
+    }
+    TOTAL_OPEN_ORDERS.update(storage, |count| -> StdResult<_> {
+        Ok(count.saturating_sub(1u64))
+    })?;
+    let user_count: u64 = OPEN_ORDERS_BY_USER
+        .may_load(storage, order.bidder_addr.as_bytes())?
+        .unwrap_or(0u64)
+        .saturating_sub(1u64);
+    if user_count == 0 {
+        OPEN_ORDERS_BY_USER.remove(storage, order.bidder_addr.as_bytes());
+    } else {
+        OPEN_ORDERS_BY_USER.save(storage, order.bidder_addr.as_bytes(), &user_count)?;
+    }
+
+    Ok(())
+}
+
+/// Adds the asset to the offer escrowed for the bidder
+pub fn increase_escrowed_offer(
+    storage: &mut dyn Storage,
+    bidder_addr: &Addr,
+    asset: &Asset,
+) -> StdResult<()> {
+    ESCROWED_OFFER_BY_USER.update(
+        storage,
+        (bidder_addr.as_bytes(), asset.info.to_string().as_bytes()),
+        |escrowed| -> StdResult<_> { Ok(escrowed.unwrap_or_default().checked_add(asset.amount)?) },
+    )?;
+
+    Ok(())
+}
+
+/// Subtracts the asset from the offer escrowed for the bidder, assets without escrow are removed
+pub fn decrease_escrowed_offer(
+    storage: &mut dyn Storage,
+    bidder_addr: &Addr,
+    asset: &Asset,
+) -> StdResult<()> {
+    let key: String = asset.info.to_string();
+    let escrowed: Uint128 = ESCROWED_OFFER_BY_USER
+        .may_load(storage, (bidder_addr.as_bytes(), key.as_bytes()))?
+        .unwrap_or_default()
+        .checked_sub(asset.amount)?;
+    if escrowed.is_zero() {
+        ESCROWED_OFFER_BY_USER.remove(storage, (bidder_addr.as_bytes(), key.as_bytes()));
+    } else {
+        ESCROWED_OFFER_BY_USER.save(
+            storage,
+            (bidder_addr.as_bytes(), key.as_bytes()),
+            &escrowed,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Adds amount to the open fees of the fee asset of the order
+pub fn increase_open_fees(
+    storage: &mut dyn Storage,
+    order: &OrderInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let config: Config = CONFIG.load(storage)?;
+    if !config.fee_denominated_in_ask {
+        TOTAL_OPEN_FEES.update(storage, |total| -> StdResult<_> {
+            Ok(total.checked_add(amount)?)
+        })?;
+        return Ok(());
+    }
+
+    OPEN_ASK_FEES.update(
+        storage,
+        order.ask_asset.info.to_string().as_bytes(),
+        |open_fees| -> StdResult<_> {
+            let mut open_fees: Asset = open_fees.unwrap_or(Asset {
+                info: order.ask_asset.info.clone(),
+                amount: Uint128::zero(),
+            });
+            open_fees.amount = open_fees.amount.checked_add(amount)?;
+            Ok(open_fees)
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Subtracts amount from the open fees of the fee asset of the order, assets without
+/// open fees are removed
+pub fn decrease_open_fees(
+    storage: &mut dyn Storage,
+    order: &OrderInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    let config: Config = CONFIG.load(storage)?;
+    if !config.fee_denominated_in_ask {
+        TOTAL_OPEN_FEES.update(storage, |total| -> StdResult<_> {
+            Ok(total.checked_sub(amount)?)
+        })?;
+        return Ok(());
+    }
+
+    let key: String = order.ask_asset.info.to_string();
+    let mut open_fees: Asset = OPEN_ASK_FEES.load(storage, key.as_bytes())?;
+    open_fees.amount = open_fees.amount.checked_sub(amount)?;
+    if open_fees.amount.is_zero() {
+        OPEN_ASK_FEES.remove(storage, key.as_bytes());
+    } else {
+        OPEN_ASK_FEES.save(storage, key.as_bytes(), &open_fees)?;
+    }
+
+    Ok(())
+}
+
+pub fn assert_assets_not_blocked(
+    storage: &dyn Storage,
+    asset_infos: &[AssetInfo],
+) -> StdResult<()> {
+    for asset_info in asset_infos.iter() {
+        if BLOCKED_ASSETS.has(storage, asset_info.to_string()) {
+            return Err(StdError::generic_err(format!(
+                "asset {} is blocked",
+                asset_info
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the fee against the min fee of the pair, raised by the dynamic min fee if any
+pub fn assert_min_fee(
+    storage: &dyn Storage,
+    config: &Config,
+    pair_addr: &Addr,
+    fee_amount: Uint128,
+    dynamic_min_fee: Uint128,
+) -> StdResult<()> {
+    let min_fee_amount: Uint128 = MIN_FEE_BY_PAIR
+        .may_load(storage, pair_addr.as_bytes())?
+        .unwrap_or(config.min_fee_amount)
+        .checked_add(dynamic_min_fee)?;
+    if fee_amount < min_fee_amount {
+        return Err(StdError::generic_err(format!(
+            "fee should be greater than {}",
+            min_fee_amount
+        )));
+    }
+
+    Ok(())
+}
+
+/// Guards against a factory returning a pair which doesn't trade both assets
+pub fn assert_pair_assets(pair_info: &PairInfo, asset_infos: &[AssetInfo]) -> StdResult<()> {
+    for asset_info in asset_infos.iter() {
+        if !pair_info.asset_infos.contains(asset_info) {
+            return Err(StdError::generic_err(format!(
+                "pair {} does not trade {}",
+                pair_info.contract_addr, asset_info
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects bidder cancels of orders younger than min_order_lifetime_seconds, unless expired
+pub fn assert_min_lifetime(config: &Config, order: &OrderInfo, now: u64) -> StdResult<()> {
+    let min_cancel_time: u64 = order.created_at + config.min_order_lifetime_seconds;
+    if now < min_cancel_time && !order.is_expired(now) {
+        return Err(StdError::generic_err(format!(
+            "order {} cannot be canceled before {}",
+            order.order_id, min_cancel_time
+        )));
+    }
+
+    Ok(())
+}
+
+/// Rejects the execution of an order that is expired, older than the max order age, whose
+/// fee would exceed what the executor can still earn this epoch, or whose group escrow
+/// can no longer cover its offer
+pub fn assert_executable(
+    storage: &dyn Storage,
+    config: &Config,
+    order: &OrderInfo,
+    executor: &Addr,
+    now: u64,
+) -> StdResult<()> {
+    if order.is_expired(now) {
+        return Err(StdError::generic_err("order expired"));
+    }
+
+    // abandoned orders don't fill at stale prices
+    if matches!(config.max_order_age_seconds, Some(max_age) if now > order.created_at + max_age) {
+        return Err(StdError::generic_err(
+            "order is older than the max order age",
+        ));
+    }
+
+    if let Some(max_fee_per_epoch) = config.max_fee_per_epoch {
+        let epoch: u64 = config.epoch(now);
+        let fee_earned: Uint128 = FEE_EARNED_EPOCH
+            .may_load(storage, (executor.as_bytes(), &epoch.to_be_bytes()))?
+            .unwrap_or_default();
+        let fee_amount: Uint128 = order.effective_fee(now, config.fee_bump_period);
+        if fee_earned.checked_add(fee_amount)? > max_fee_per_epoch {
+            return Err(StdError::generic_err(format!(
+                "executor fee cap of {} per epoch reached",
+                max_fee_per_epoch
+            )));
+        }
+    }
+
+    // the levels of a group share an escrow which earlier fills may have drawn down
+    if let Some(group_id) = order.group_id {
+        let group: OrderGroupInfo = ORDER_GROUPS.load(storage, &group_id.to_be_bytes())?;
+        if group.escrow_asset.amount < order.offer_asset.amount {
+            return Err(StdError::generic_err("insufficient group escrow"));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn assert_pair_not_paused(storage: &dyn Storage, pair_addr: &Addr) -> StdResult<()> {
+    if PAUSED_PAIRS.has(storage, pair_addr.as_bytes()) {
+        return Err(StdError::generic_err(format!(
+            "pair {} is paused",
+            pair_addr
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn store_new_order_group(
+    storage: &mut dyn Storage,
+    group: &mut OrderGroupInfo,
+) -> StdResult<()> {
+    let new_id: u64 = LAST_ORDER_GROUP_ID.load(storage)? + 1u64;
+    group.group_id = new_id;
+
+    ORDER_GROUPS.save(storage, &new_id.to_be_bytes(), group)?;
+    LAST_ORDER_GROUP_ID.save(storage, &new_id)?;
+    increase_escrowed_offer(storage, &group.bidder_addr, &group.escrow_asset)?;
+
+    Ok(())
+}
+
+/// amount * ratio rounded down, None instead of a panic when the product does not fit a Uint128
+pub fn checked_mul_decimal(amount: Uint128, ratio: Decimal) -> Option<Uint128> {
+    (amount.full_mul(ratio.numerator()) / Uint256::from(ratio.denominator()))
+        .try_into()
+        .ok()
 }
 
 // settings for pagination
-const MAX_LIMIT: u32 = 30;
+pub const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
 
+/// Page size for the limit provided, a missing or zero limit falls back to DEFAULT_LIMIT
+pub fn page_limit(limit: Option<u32>) -> usize {
+    match limit {
+        None | Some(0) => DEFAULT_LIMIT as usize,
+        Some(limit) => limit.min(MAX_LIMIT) as usize,
+    }
+}
+
 pub fn read_orders_by_user(
     storage: &dyn Storage,
     user: &Addr,
@@ -88,16 +882,16 @@ pub fn read_orders_by_user(
     limit: Option<u32>,
     order_by: Option<OrderBy>,
 ) -> StdResult<Vec<OrderInfo>> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let limit = page_limit(limit);
     let (start, end, order_by) = match order_by {
         Some(OrderBy::Asc) => (
-            calc_range_start(start_after).map(Bound::exclusive),
+            start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec())),
             None,
             Order::Ascending,
         ),
         _ => (
             None,
-            calc_range_end(start_after).map(Bound::exclusive),
+            start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec())),
             Order::Descending,
         ),
     };
@@ -105,11 +899,11 @@ pub fn read_orders_by_user(
     ORDERS_BY_USER
         .prefix(user.as_bytes())
         .range(storage, start, end, order_by)
-        .take(limit)
         .map(|item| {
             let (k, _) = item?;
             ORDERS.load(storage, &k)
         })
+        .take(limit)
         .collect()
 }
 
@@ -119,23 +913,180 @@ pub fn read_orders(
     limit: Option<u32>,
     order_by: Option<OrderBy>,
 ) -> StdResult<Vec<OrderInfo>> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let limit = page_limit(limit);
     let (start, end, order_by) = match order_by {
         Some(OrderBy::Asc) => (
-            calc_range_start(start_after).map(Bound::exclusive),
+            start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec())),
             None,
             Order::Ascending,
         ),
         _ => (
             None,
-            calc_range_end(start_after).map(Bound::exclusive),
+            start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec())),
             Order::Descending,
         ),
     };
 
     ORDERS
         .range(storage, start, end, order_by)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .take(limit)
+        .collect()
+}
+
+pub fn read_orders_by_user_pair(
+    storage: &dyn Storage,
+    user: &Addr,
+    pair_addr: &Addr,
+    limit: Option<u32>,
+) -> StdResult<Vec<OrderInfo>> {
+    let limit = page_limit(limit);
+
+    ORDERS_BY_USER_PAIR
+        .prefix((user.as_bytes(), pair_addr.as_bytes()))
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            ORDERS.load(storage, &k)
+        })
+        .collect()
+}
+
+pub fn read_order_counts_by_pair(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, u64)>> {
+    let limit = page_limit(limit);
+    let start = start_after.map(|pair_addr| Bound::exclusive(pair_addr.as_bytes()));
+
+    PAIRS_WITH_ORDERS
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, count) = item?;
+            let pair_addr =
+                String::from_utf8(k).map_err(|_| StdError::generic_err("invalid pair address"))?;
+            Ok((pair_addr, count))
+        })
+        .collect()
+}
+
+pub fn read_bidder_order_totals(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, u64)>> {
+    let limit = page_limit(limit);
+    let start = start_after.map(|bidder_addr| Bound::exclusive(bidder_addr.as_bytes()));
+
+    BIDDER_ORDER_TOTALS
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, total) = item?;
+            let bidder_addr = String::from_utf8(k)
+                .map_err(|_| StdError::generic_err("invalid bidder address"))?;
+            Ok((bidder_addr, total))
+        })
+        .collect()
+}
+
+pub fn read_orders_by_ask_asset(
+    storage: &dyn Storage,
+    ask_asset_info: &AssetInfo,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<OrderInfo>> {
+    let limit = page_limit(limit);
+    let start = start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec()));
+
+    ASK_ASSET_INDEX
+        .prefix(ask_asset_info.to_string().as_bytes())
+        .range(storage, start, None, Order::Ascending)
         .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            ORDERS.load(storage, &k)
+        })
+        .collect()
+}
+
+pub fn read_orders_by_denom(
+    storage: &dyn Storage,
+    denom: &str,
+    side: OrderSide,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<OrderInfo>> {
+    let limit = page_limit(limit);
+    let start = start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec()));
+    let index = match side {
+        OrderSide::Offer => OFFER_ASSET_INDEX,
+        OrderSide::Ask => ASK_ASSET_INDEX,
+    };
+
+    index
+        .prefix(denom.as_bytes())
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            ORDERS.load(storage, &k)
+        })
+        .collect()
+}
+
+/// Reads the orders offering the asset, then the orders asking it, from the asset indexes
+pub fn read_orders_by_asset(
+    storage: &dyn Storage,
+    asset_info: &AssetInfo,
+    limit: Option<u32>,
+) -> StdResult<Vec<OrderInfo>> {
+    let limit = page_limit(limit);
+    let asset_key: String = asset_info.to_string();
+
+    OFFER_ASSET_INDEX
+        .prefix(asset_key.as_bytes())
+        .range(storage, None, None, Order::Ascending)
+        .chain(ASK_ASSET_INDEX.prefix(asset_key.as_bytes()).range(
+            storage,
+            None,
+            None,
+            Order::Ascending,
+        ))
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            ORDERS.load(storage, &k)
+        })
+        .collect()
+}
+
+pub fn has_open_orders(storage: &dyn Storage, user: &Addr) -> bool {
+    ORDERS_BY_USER
+        .prefix(user.as_bytes())
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+/// Reads the existing orders with from_id <= order_id <= to_id, capped at MAX_LIMIT
+pub fn read_order_range(
+    storage: &dyn Storage,
+    from_id: u64,
+    to_id: u64,
+) -> StdResult<Vec<OrderInfo>> {
+    let start = Bound::inclusive(from_id.to_be_bytes().to_vec());
+    let end = Bound::inclusive(to_id.to_be_bytes().to_vec());
+
+    ORDERS
+        .range(storage, Some(start), Some(end), Order::Ascending)
+        .take(MAX_LIMIT as usize)
         .map(|item| {
             let (_, v) = item?;
             Ok(v)
@@ -143,16 +1094,106 @@ pub fn read_orders(
         .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<u64>) -> Option<Vec<u8>> {
-    start_after.map(|id| {
-        let mut v = id.to_be_bytes().to_vec();
-        v.push(1);
-        v
-    })
+/// Reads up to limit orders of the pair after the start_after position, side by side,
+/// lowest implied price first within a side
+pub fn read_orders_by_pair(
+    storage: &dyn Storage,
+    pair_addr: &Addr,
+    start_after: Option<&PriceCursor>,
+    limit: usize,
+) -> StdResult<Vec<OrderInfo>> {
+    // under the pair, a key is the length-prefixed offer asset followed by the price key
+    let start = start_after.map(|cursor| {
+        let offer_asset: String = cursor.offer_asset_info.to_string();
+        let mut key: Vec<u8> = (offer_asset.len() as u16).to_be_bytes().to_vec();
+        key.extend_from_slice(offer_asset.as_bytes());
+        key.extend_from_slice(&cursor.price.u128().to_be_bytes());
+        key.extend_from_slice(&cursor.order_id.to_be_bytes());
+        Bound::exclusive(key)
+    });
+
+    ORDERS_BY_PRICE
+        .sub_prefix(pair_addr.as_bytes())
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            // the order id is the last 8 bytes of the price key
+            ORDERS.load(storage, &k[k.len() - 8..])
+        })
+        .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_end(start_after: Option<u64>) -> Option<Vec<u8>> {
-    start_after.map(|id| id.to_be_bytes().to_vec())
+/// Reads the fills of the pair, most recent order first
+pub fn read_fills(
+    storage: &dyn Storage,
+    pair_addr: &Addr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<FillInfo>> {
+    let end = start_after.map(|id| Bound::exclusive(id.to_be_bytes().to_vec()));
+
+    FILLS
+        .prefix(pair_addr.as_bytes())
+        .range(storage, None, end, Order::Descending)
+        .take(page_limit(limit))
+        .map(|item| {
+            let (_, fill) = item?;
+            Ok(fill)
+        })
+        .collect()
+}
+
+/// Reads up to MAX_LIMIT orders of the pair offering the asset, lowest implied price first
+pub fn read_orders_by_pair_side(
+    storage: &dyn Storage,
+    pair_addr: &Addr,
+    offer_asset_info: &AssetInfo,
+) -> StdResult<Vec<OrderInfo>> {
+    ORDERS_BY_PRICE
+        .prefix((
+            pair_addr.as_bytes(),
+            offer_asset_info.to_string().as_bytes(),
+        ))
+        .range(storage, None, None, Order::Ascending)
+        .take(MAX_LIMIT as usize)
+        .map(|item| {
+            let (k, _) = item?;
+            // the order id is the last 8 bytes of the price key
+            ORDERS.load(storage, &k[k.len() - 8..])
+        })
+        .collect()
+}
+
+/// Reads the orders of a pair offering the asset whose implied price is within
+/// [min_price, max_price], ascending by price
+pub fn read_orders_by_price(
+    storage: &dyn Storage,
+    pair_addr: &Addr,
+    offer_asset_info: &AssetInfo,
+    min_price: Uint128,
+    max_price: Uint128,
+    limit: Option<u32>,
+) -> StdResult<Vec<OrderInfo>> {
+    let limit = page_limit(limit);
+    // the price keys are suffixed with the order id, so the upper bound is the next price
+    let start = Bound::inclusive(min_price.u128().to_be_bytes().to_vec());
+    let end = max_price
+        .u128()
+        .checked_add(1u128)
+        .map(|price| Bound::exclusive(price.to_be_bytes().to_vec()));
+
+    ORDERS_BY_PRICE
+        .prefix((
+            pair_addr.as_bytes(),
+            offer_asset_info.to_string().as_bytes(),
+        ))
+        .range(storage, Some(start), end, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (k, _) = item?;
+            // the order id is the last 8 bytes of the price key
+            ORDERS.load(storage, &k[k.len() - 8..])
+        })
+        .collect()
 }
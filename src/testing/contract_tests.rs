@@ -0,0 +1,229 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{Addr, Decimal, Order, StdError, Storage, Uint128};
+use terraswap::asset::Asset;
+
+use crate::contract::{instantiate, migrate};
+use crate::msg::{InstantiateMsg, MigrateMsg};
+use crate::state::{
+    Config, OrderInfo, BIDDER_ORDER_TOTALS, CONFIG, LAST_ORDER_ID, LEGACY_ORDERS, ORDERS,
+    ORDERS_BY_PRICE, ORDERS_BY_USER, TOTAL_OPEN_FEES, TOTAL_OPEN_ORDERS,
+};
+use crate::testing::mock_querier::mock_dependencies;
+use crate::testing::{instantiate_msg, offer_token, uluna, BIDDER, FACTORY, OFFER_TOKEN, PAIR};
+
+fn initial_order(order_id: u64) -> OrderInfo {
+    OrderInfo {
+        order_id,
+        bidder_addr: Addr::unchecked(BIDDER),
+        factory_addr: Addr::unchecked(FACTORY),
+        pair_addr: Addr::unchecked(PAIR),
+        offer_asset: Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        ask_asset: Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        offer_decimals: 6,
+        ask_decimals: 6,
+        fee_amount: Uint128::from(10u128),
+        fee_bond: Uint128::zero(),
+        created_at: 100u64,
+        expires_at: None,
+        group_id: None,
+        order_hash: String::new(),
+        grid: None,
+        offer_fee: Uint128::zero(),
+        ramp_seconds: None,
+        fill_hook: None,
+        reserve_price: None,
+        max_spread: None,
+    }
+}
+
+#[test]
+fn seeds_the_initial_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            initial_orders: Some(vec![initial_order(3u64), initial_order(7u64)]),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(LAST_ORDER_ID.load(&deps.storage).unwrap(), 7u64);
+    assert_eq!(
+        BIDDER_ORDER_TOTALS
+            .load(&deps.storage, BIDDER.as_bytes())
+            .unwrap(),
+        2u64
+    );
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::from(20u128)
+    );
+    let order: OrderInfo = ORDERS.load(&deps.storage, &7u64.to_be_bytes()).unwrap();
+    assert_eq!(order.order_hash, order.compute_hash());
+}
+
+#[test]
+fn validates_the_initial_orders() {
+    let instantiate_with = |order: OrderInfo| {
+        let mut deps = mock_dependencies(&[]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                initial_orders: Some(vec![order]),
+                ..instantiate_msg()
+            },
+        )
+        .unwrap_err()
+    };
+
+    let mut order = initial_order(1u64);
+    order.offer_asset.amount = Uint128::zero();
+    assert_eq!(
+        instantiate_with(order),
+        StdError::generic_err("offer amount must be greater than zero")
+    );
+
+    let mut order = initial_order(1u64);
+    order.fee_amount = Uint128::from(4u128);
+    assert_eq!(
+        instantiate_with(order),
+        StdError::generic_err("fee should be greater than 5")
+    );
+
+    let mut order = initial_order(1u64);
+    order.group_id = Some(1u64);
+    assert_eq!(
+        instantiate_with(order),
+        StdError::generic_err("initial orders must not belong to a group")
+    );
+
+    let mut order = initial_order(1u64);
+    order.bidder_addr = Addr::unchecked("a");
+    assert!(matches!(
+        instantiate_with(order),
+        StdError::GenericErr { .. }
+    ));
+}
+
+#[test]
+fn instantiate_validates_the_treasury_split() {
+    let mut deps = mock_dependencies(&[]);
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            protocol_fee_bps: Some(10001u64),
+            treasury: Some("treasury0000".to_string()),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("protocol_fee_bps must not exceed 10000")
+    );
+
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            protocol_fee_bps: Some(100u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("treasury is required with a protocol fee")
+    );
+
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            protocol_fee_bps: Some(100u64),
+            treasury: Some("treasury0000".to_string()),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let config: Config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.protocol_fee_bps, 100u64);
+    assert_eq!(config.treasury, Some(Addr::unchecked("treasury0000")));
+}
+
+#[test]
+fn migrates_the_state_of_the_first_version() {
+    let mut deps = mock_dependencies(&[]);
+    // config and order as serialized by the first version
+    deps.storage.set(
+        b"config",
+        br#"{"fee_token":"fee0000","min_fee_amount":"5","terraswap_factory":"factory0000"}"#,
+    );
+    deps.storage.set(
+        &LEGACY_ORDERS.key(&1u64.to_be_bytes()),
+        br#"{"order_id":1,"bidder_addr":"addr0000","pair_addr":"pair0000",
+            "offer_asset":{"info":{"token":{"contract_addr":"token0000"}},"amount":"1000"},
+            "ask_asset":{"info":{"native_token":{"denom":"uluna"}},"amount":"900"},
+            "fee_amount":"10"}"#,
+    );
+    deps.storage.set(b"last_order_id", b"1");
+
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { owner: None }).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("owner is required to migrate from the first version")
+    );
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            owner: Some("owner".to_string()),
+        },
+    )
+    .unwrap();
+
+    let config: Config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.owner, Addr::unchecked("owner"));
+    assert_eq!(config.min_fee_amount, Uint128::from(5u128));
+    assert_eq!(config.max_spread, Decimal::percent(5));
+
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.factory_addr, Addr::unchecked(FACTORY));
+    assert_eq!(order.created_at, mock_env().block.time.seconds());
+    assert_eq!(order.order_hash, order.compute_hash());
+    assert_eq!((order.offer_decimals, order.ask_decimals), (6u8, 6u8));
+    assert_eq!(LAST_ORDER_ID.load(&deps.storage).unwrap(), 1u64);
+    assert_eq!(TOTAL_OPEN_ORDERS.load(&deps.storage).unwrap(), 1u64);
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::from(10u128)
+    );
+    assert!(ORDERS_BY_USER.has(&deps.storage, (BIDDER.as_bytes(), &1u64.to_be_bytes())));
+    assert!(ORDERS_BY_PRICE
+        .prefix((PAIR.as_bytes(), OFFER_TOKEN.as_bytes()))
+        .range(&deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some());
+
+    // a second migration leaves the migrated state as is
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { owner: None }).unwrap();
+    assert_eq!(
+        ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap(),
+        order
+    );
+}
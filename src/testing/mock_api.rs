@@ -0,0 +1,73 @@
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{Addr, Api, CanonicalAddr, RecoverPubkeyError, StdResult, VerificationError};
+
+// length of the canonical address of a public key, ripemd160(sha256(public key))
+const KEY_ADDR_LENGTH: usize = 20;
+
+/// MockApi which also humanizes the canonical address of a public key, as its hex encoding.
+/// MockApi only humanizes the canonical addresses it created itself
+#[derive(Default)]
+pub struct MockKeyApi {
+    base: MockApi,
+}
+
+impl Api for MockKeyApi {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        self.base.addr_validate(human)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        self.base.addr_canonicalize(human)
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        if canonical.len() == KEY_ADDR_LENGTH {
+            return Ok(Addr::unchecked(hex::encode(canonical.as_slice())));
+        }
+
+        self.base.addr_humanize(canonical)
+    }
+
+    fn secp256k1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.base
+            .secp256k1_verify(message_hash, signature, public_key)
+    }
+
+    fn secp256k1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.base
+            .secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+    }
+
+    fn ed25519_verify(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.base.ed25519_verify(message, signature, public_key)
+    }
+
+    fn ed25519_batch_verify(
+        &self,
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        public_keys: &[&[u8]],
+    ) -> Result<bool, VerificationError> {
+        self.base
+            .ed25519_batch_verify(messages, signatures, public_keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.base.debug(message)
+    }
+}
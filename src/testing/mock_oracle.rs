@@ -0,0 +1,78 @@
+use cosmwasm_std::{Addr, Decimal, QuerierWrapper, StdError, StdResult, Uint128};
+use terraswap::asset::{Asset, AssetInfo, PairInfo};
+use terraswap::pair::SimulationResponse;
+
+use crate::querier::PairOracle;
+
+/// PairOracle answering from the pairs and prices it was given, a swap returns
+/// the offer amount times the price of its direction, without spread or commission
+#[derive(Default)]
+pub struct MockOracle {
+    // factory and pair
+    pairs: Vec<(String, PairInfo)>,
+    // pair, offer asset and return per unit of offer
+    prices: Vec<(String, AssetInfo, Decimal)>,
+}
+
+impl MockOracle {
+    pub fn with_pair(mut self, factory: &str, pair: &str, asset_infos: [AssetInfo; 2]) -> Self {
+        self.pairs.push((
+            factory.to_string(),
+            PairInfo {
+                asset_infos,
+                contract_addr: pair.to_string(),
+                liquidity_token: format!("{}_lp", pair),
+            },
+        ));
+        self
+    }
+
+    pub fn with_price(mut self, pair: &str, offer_asset_info: AssetInfo, price: Decimal) -> Self {
+        self.prices
+            .retain(|(p, info, _)| !(p == pair && *info == offer_asset_info));
+        self.prices
+            .push((pair.to_string(), offer_asset_info, price));
+        self
+    }
+}
+
+impl PairOracle for MockOracle {
+    fn query_pair_info(
+        &self,
+        _querier: &QuerierWrapper,
+        factory_addr: Addr,
+        asset_infos: &[AssetInfo; 2],
+    ) -> StdResult<PairInfo> {
+        self.pairs
+            .iter()
+            .find(|(factory, pair_info)| {
+                factory == factory_addr.as_str()
+                    && ((pair_info.asset_infos[0] == asset_infos[0]
+                        && pair_info.asset_infos[1] == asset_infos[1])
+                        || (pair_info.asset_infos[0] == asset_infos[1]
+                            && pair_info.asset_infos[1] == asset_infos[0]))
+            })
+            .map(|(_, pair_info)| pair_info.clone())
+            .ok_or_else(|| StdError::generic_err("no pair"))
+    }
+
+    fn simulate(
+        &self,
+        _querier: &QuerierWrapper,
+        pair_addr: Addr,
+        offer_asset: &Asset,
+    ) -> StdResult<SimulationResponse> {
+        let price: Decimal = self
+            .prices
+            .iter()
+            .find(|(pair, info, _)| pair == pair_addr.as_str() && *info == offer_asset.info)
+            .map(|(_, _, price)| *price)
+            .ok_or_else(|| StdError::generic_err("no price"))?;
+
+        Ok(SimulationResponse {
+            return_amount: offer_asset.amount * price,
+            spread_amount: Uint128::zero(),
+            commission_amount: Uint128::zero(),
+        })
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+};
+use cw20::{AllowanceResponse, BalanceResponse, Cw20QueryMsg, Expiration, TokenInfoResponse};
+use terraswap::asset::Asset;
+use terraswap::pair::{PoolResponse, QueryMsg as PairQueryMsg};
+
+/// mock_dependencies replacement answering the cw20 and pair pool queries of the contract,
+/// tokens have 6 decimals unless set otherwise
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let custom_querier: WasmMockQuerier =
+        WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]));
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    token_balances: HashMap<String, HashMap<String, Uint128>>,
+    token_decimals: HashMap<String, u8>,
+    // token and (owner, spender)
+    token_allowances: HashMap<String, HashMap<(String, String), Uint128>>,
+    pools: HashMap<String, [Asset; 2]>,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            token_balances: HashMap::new(),
+            token_decimals: HashMap::new(),
+            token_allowances: HashMap::new(),
+            pools: HashMap::new(),
+        }
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                if self.pools.contains_key(contract_addr) =>
+            {
+                match from_binary(msg) {
+                    Ok(PairQueryMsg::Pool {}) => {
+                        SystemResult::Ok(ContractResult::from(to_binary(&PoolResponse {
+                            assets: self.pools[contract_addr].clone(),
+                            total_share: Uint128::zero(),
+                        })))
+                    }
+                    _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                        kind: format!("query to {}", contract_addr),
+                    }),
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(msg) {
+                Ok(Cw20QueryMsg::TokenInfo {}) => {
+                    SystemResult::Ok(ContractResult::from(to_binary(&TokenInfoResponse {
+                        name: contract_addr.to_string(),
+                        symbol: "TKN".to_string(),
+                        decimals: self
+                            .token_decimals
+                            .get(contract_addr)
+                            .copied()
+                            .unwrap_or(6u8),
+                        total_supply: Uint128::zero(),
+                    })))
+                }
+                Ok(Cw20QueryMsg::Balance { address }) => {
+                    let balance: Uint128 = self
+                        .token_balances
+                        .get(contract_addr)
+                        .and_then(|balances| balances.get(&address))
+                        .copied()
+                        .unwrap_or_default();
+                    SystemResult::Ok(ContractResult::from(to_binary(&BalanceResponse {
+                        balance,
+                    })))
+                }
+                Ok(Cw20QueryMsg::Allowance { owner, spender }) => {
+                    let allowance: Uint128 = self
+                        .token_allowances
+                        .get(contract_addr)
+                        .and_then(|allowances| allowances.get(&(owner, spender)))
+                        .copied()
+                        .unwrap_or_default();
+                    SystemResult::Ok(ContractResult::from(to_binary(&AllowanceResponse {
+                        allowance,
+                        expires: Expiration::Never {},
+                    })))
+                }
+                _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: format!("query to {}", contract_addr),
+                }),
+            },
+            _ => self.base.handle_query(request),
+        }
+    }
+
+    /// Sets the balances of a token, replacing its previous balances
+    pub fn with_token_balances(&mut self, balances: &[(&str, &[(&str, u128)])]) {
+        for (token, token_balances) in balances.iter() {
+            self.token_balances.insert(
+                token.to_string(),
+                token_balances
+                    .iter()
+                    .map(|(addr, balance)| (addr.to_string(), Uint128::from(*balance)))
+                    .collect(),
+            );
+        }
+    }
+
+    /// Sets the allowance of the owner to the spender, never expiring
+    pub fn with_token_allowance(&mut self, token: &str, owner: &str, spender: &str, amount: u128) {
+        self.token_allowances
+            .entry(token.to_string())
+            .or_default()
+            .insert(
+                (owner.to_string(), spender.to_string()),
+                Uint128::from(amount),
+            );
+    }
+
+    pub fn with_token_decimals(&mut self, token: &str, decimals: u8) {
+        self.token_decimals.insert(token.to_string(), decimals);
+    }
+
+    pub fn with_pool(&mut self, pair: &str, assets: [Asset; 2]) {
+        self.pools.insert(pair.to_string(), assets);
+    }
+
+    pub fn update_balance(&mut self, addr: &str, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+}
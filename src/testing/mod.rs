@@ -0,0 +1,186 @@
+mod contract_tests;
+mod mock_api;
+mod mock_oracle;
+mod mock_querier;
+mod order_group_tests;
+mod order_tests;
+mod owner_tests;
+mod query_tests;
+mod state_tests;
+
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    Coin, ContractResult, Decimal, DepsMut, OwnedDeps, Response, StdResult,
+    SubMsgExecutionResponse, Uint128,
+};
+use terraswap::asset::{Asset, AssetInfo};
+
+use crate::msg::InstantiateMsg;
+use crate::order::{execute_order, execute_order_reply, submit_order};
+use crate::state::LAST_ORDER_ID;
+use crate::testing::mock_oracle::MockOracle;
+use crate::testing::mock_querier::WasmMockQuerier;
+
+// fixtures shared by the tests
+pub const FEE_TOKEN: &str = "fee0000";
+pub const FACTORY: &str = "factory0000";
+pub const PAIR: &str = "pair0000";
+pub const OFFER_TOKEN: &str = "token0000";
+pub const BIDDER: &str = "addr0000";
+pub const EXECUTOR: &str = "executor0000";
+
+pub fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        fee_token: FEE_TOKEN.to_string(),
+        min_fee_amount: Uint128::from(5u128),
+        terraswap_factory: FACTORY.to_string(),
+        fallback_factory: None,
+        cleanup_reward_bps: None,
+        max_spread: None,
+        fee_bump_period: None,
+        notional_asset_info: None,
+        min_notional: None,
+        default_order_ttl_seconds: None,
+        allow_offer_fee: None,
+        dust_threshold: None,
+        initial_orders: None,
+        dynamic_fee: None,
+        protocol_fee_bps: None,
+        treasury: None,
+        native_refund_dust: None,
+        min_order_lifetime_seconds: None,
+        max_fee_per_epoch: None,
+        epoch_seconds: None,
+        fee_denominated_in_ask: None,
+        bidder_rebate_bps: None,
+        max_order_age_seconds: None,
+        incentive_token: None,
+        incentive_per_fill: None,
+        retain_fills: None,
+    }
+}
+
+pub fn offer_token() -> AssetInfo {
+    AssetInfo::Token {
+        contract_addr: OFFER_TOKEN.to_string(),
+    }
+}
+
+pub fn uluna() -> AssetInfo {
+    AssetInfo::NativeToken {
+        denom: "uluna".to_string(),
+    }
+}
+
+pub fn mock_oracle(price: Decimal) -> MockOracle {
+    MockOracle::default()
+        .with_pair(FACTORY, PAIR, [offer_token(), uluna()])
+        .with_price(PAIR, offer_token(), price)
+}
+
+/// Submits an order of BIDDER offering token0000 for uluna, returns its id
+pub fn submit_token_order(
+    mut deps: DepsMut,
+    oracle: &MockOracle,
+    offer_amount: u128,
+    ask_amount: u128,
+    fee_amount: u128,
+) -> u64 {
+    try_submit_token_order(deps.branch(), oracle, offer_amount, ask_amount, fee_amount).unwrap();
+
+    LAST_ORDER_ID.load(deps.storage).unwrap()
+}
+
+/// Submits an order of the bidder offering 1000 of the token for 900 uluna
+pub fn submit_order_as(deps: DepsMut, oracle: &MockOracle, bidder: &str, offer_token: &str) {
+    submit_order(
+        deps,
+        mock_env(),
+        mock_info(bidder, &[]),
+        oracle,
+        Asset {
+            info: AssetInfo::Token {
+                contract_addr: offer_token.to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+pub fn try_submit_token_order(
+    deps: DepsMut,
+    oracle: &MockOracle,
+    offer_amount: u128,
+    ask_amount: u128,
+    fee_amount: u128,
+) -> StdResult<Response> {
+    submit_order(
+        deps,
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(offer_amount),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(ask_amount),
+        },
+        Uint128::from(fee_amount),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+}
+
+/// Executes the order as EXECUTOR and settles it, the swap leaves the contract
+/// holding ask_balance_after uluna
+pub fn fill_order(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    oracle: &MockOracle,
+    order_id: u64,
+    ask_balance_after: u128,
+) -> Response {
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        oracle,
+        order_id,
+        None,
+        None,
+    )
+    .unwrap();
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![Coin::new(ask_balance_after, "uluna")],
+    );
+
+    execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap()
+}
@@ -0,0 +1,555 @@
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    to_binary, BankMsg, Coin, ContractResult, CosmosMsg, Decimal, DepsMut, StdError,
+    SubMsgExecutionResponse, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terraswap::asset::{Asset, AssetInfo};
+
+use crate::contract::instantiate;
+use crate::msg::{InstantiateMsg, OrderGroupLevel};
+use crate::order::{cancel_order, execute_order, execute_order_reply};
+use crate::order_group::{cancel_order_group, submit_order_group};
+use crate::query::query_order_with_bidder_context;
+use crate::state::{OrderGroupInfo, OrderInfo, ACCRUED_NATIVE_DUST, ORDERS, ORDER_GROUPS};
+use crate::testing::mock_oracle::MockOracle;
+use crate::testing::mock_querier::mock_dependencies;
+use crate::testing::{
+    fill_order, instantiate_msg, mock_oracle, offer_token, submit_token_order, uluna, BIDDER,
+    EXECUTOR, FACTORY, FEE_TOKEN, OFFER_TOKEN, PAIR,
+};
+
+fn level(offer_amount: u128, ask_amount: u128) -> OrderGroupLevel {
+    OrderGroupLevel {
+        offer_amount: Uint128::from(offer_amount),
+        ask_amount: Uint128::from(ask_amount),
+    }
+}
+
+fn transfer_from_msg(token: &str, amount: u128) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+            owner: BIDDER.to_string(),
+            recipient: MOCK_CONTRACT_ADDR.to_string(),
+            amount: Uint128::from(amount),
+        })
+        .unwrap(),
+    })
+}
+
+#[test]
+fn submit_order_group_escrows_the_offer_once() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+
+    let res = submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(600, 500), level(400, 300)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            transfer_from_msg(FEE_TOKEN, 20),
+            transfer_from_msg(OFFER_TOKEN, 1000),
+        ]
+    );
+
+    let group: OrderGroupInfo = ORDER_GROUPS
+        .load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap();
+    assert_eq!(group.order_ids, vec![1u64, 2u64]);
+    assert_eq!(group.pair_addr.as_str(), PAIR);
+    let order: OrderInfo = ORDERS.load(&deps.storage, &2u64.to_be_bytes()).unwrap();
+    assert_eq!(order.group_id, Some(1u64));
+    assert_eq!(order.offer_asset.amount, Uint128::from(400u128));
+    assert_eq!(order.factory_addr.as_str(), FACTORY);
+}
+
+#[test]
+fn submit_order_group_merges_a_fee_token_offer() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+
+    let res = submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &MockOracle::default().with_pair(FACTORY, PAIR, [fee_token.clone(), uluna()]),
+        Asset {
+            info: fee_token,
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(600, 500), level(400, 300)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![transfer_from_msg(FEE_TOKEN, 1020)]
+    );
+}
+
+#[test]
+fn submit_order_group_validates_every_level() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            notional_asset_info: Some(uluna()),
+            min_notional: Some(Uint128::from(500u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let submit = |deps: DepsMut, levels: Vec<OrderGroupLevel>| {
+        submit_order_group(
+            deps,
+            mock_env(),
+            mock_info(BIDDER, &[]),
+            &mock_oracle(Decimal::one()),
+            Asset {
+                info: offer_token(),
+                amount: Uint128::from(1000u128),
+            },
+            uluna(),
+            levels,
+            Uint128::from(10u128),
+        )
+    };
+
+    assert_eq!(
+        submit(deps.as_mut(), vec![level(600, 500), level(0, 300)]).unwrap_err(),
+        StdError::generic_err("offer amount must be greater than zero")
+    );
+    assert_eq!(
+        submit(deps.as_mut(), vec![level(600, 500), level(400, 300)]).unwrap_err(),
+        StdError::generic_err("offer notional should be greater than 500")
+    );
+    assert_eq!(
+        submit(deps.as_mut(), vec![level(1001, 500)]).unwrap_err(),
+        StdError::generic_err("level offer amount must not exceed the escrowed amount")
+    );
+    submit(deps.as_mut(), vec![level(600, 500), level(500, 400)]).unwrap();
+}
+
+#[test]
+fn submit_order_group_applies_the_dynamic_min_fee() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_pool(
+        PAIR,
+        [
+            Asset {
+                info: offer_token(),
+                amount: Uint128::from(10000u128),
+            },
+            Asset {
+                info: uluna(),
+                amount: Uint128::from(10000u128),
+            },
+        ],
+    );
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            dynamic_fee: Some(true),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let submit = |deps: DepsMut, fee_amount: u128| {
+        submit_order_group(
+            deps,
+            mock_env(),
+            mock_info(BIDDER, &[]),
+            &mock_oracle(Decimal::one()),
+            Asset {
+                info: offer_token(),
+                amount: Uint128::from(1000u128),
+            },
+            uluna(),
+            vec![level(1000, 900), level(100, 90)],
+            Uint128::from(fee_amount),
+        )
+    };
+
+    // the 1000 level is 10% of the reserve, raising the min fee of 5 by 50
+    assert_eq!(
+        submit(deps.as_mut(), 54).unwrap_err(),
+        StdError::generic_err("fee should be greater than 55")
+    );
+    submit(deps.as_mut(), 55).unwrap();
+}
+
+#[test]
+fn execute_order_checks_the_remaining_group_escrow() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(600, 500), level(500, 400)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(600u128, "uluna")]);
+    execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+    let group: OrderGroupInfo = ORDER_GROUPS
+        .load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap();
+    assert_eq!(group.escrow_asset.amount, Uint128::from(400u128));
+
+    // the second level no longer fits in the escrow, it fails before swapping
+    let err = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        2u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("insufficient group escrow"));
+}
+
+fn transfer_msg(token: &str, amount: u128) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: BIDDER.to_string(),
+            amount: Uint128::from(amount),
+        })
+        .unwrap(),
+    })
+}
+
+#[test]
+fn the_last_fill_releases_the_remaining_escrow() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(400, 350), level(500, 450)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+
+    // the swaps return 400 then 500 uluna
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (order_id, balance_after) in [(1u64, 400u128), (2u64, 900u128)] {
+        execute_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            &oracle,
+            order_id,
+            None,
+            None,
+        )
+        .unwrap();
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(balance_after, "uluna")]);
+        let res = execute_order_reply(
+            deps.as_mut(),
+            mock_env(),
+            ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        )
+        .unwrap();
+        messages = res.messages.into_iter().map(|msg| msg.msg).collect();
+    }
+
+    assert!(messages.contains(&transfer_msg(OFFER_TOKEN, 100)));
+    assert!(ORDER_GROUPS
+        .may_load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn cancel_order_group_refunds_the_escrow_and_fees() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(400, 350), level(500, 450)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+
+    let err = cancel_order_group(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), 1u64)
+        .unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+
+    let res = cancel_order_group(deps.as_mut(), mock_env(), mock_info(BIDDER, &[]), 1u64).unwrap();
+    assert_eq!(
+        res.messages
+            .into_iter()
+            .map(|msg| msg.msg)
+            .collect::<Vec<CosmosMsg>>(),
+        vec![transfer_msg(OFFER_TOKEN, 1000), transfer_msg(FEE_TOKEN, 20)]
+    );
+    assert!(ORDERS
+        .may_load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn cancel_order_group_waits_for_the_min_lifetime() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            min_order_lifetime_seconds: Some(60u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(400, 350), level(500, 450)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(59u64);
+    let err =
+        cancel_order_group(deps.as_mut(), env.clone(), mock_info(BIDDER, &[]), 1u64).unwrap_err();
+    let min_cancel_time: u64 = mock_env().block.time.seconds() + 60u64;
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "order 1 cannot be canceled before {}",
+            min_cancel_time
+        ))
+    );
+
+    env.block.time = env.block.time.plus_seconds(1u64);
+    cancel_order_group(deps.as_mut(), env, mock_info(BIDDER, &[]), 1u64).unwrap();
+    assert!(ORDER_GROUPS
+        .may_load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn group_escrow_is_counted_once_in_the_bidder_context() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 300, 250, 10);
+    submit_order_group(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        vec![level(400, 350), level(500, 450)],
+        Uint128::from(10u128),
+    )
+    .unwrap();
+
+    let res = query_order_with_bidder_context(deps.as_ref(), 2u64).unwrap();
+    assert_eq!(res.bidder_open_order_count, 3u64);
+    assert_eq!(res.bidder_escrowed_offer, Uint128::from(1300u128));
+
+    // the fill draws from the group escrow
+    fill_order(&mut deps, &oracle, 2u64, 400);
+    let res = query_order_with_bidder_context(deps.as_ref(), 3u64).unwrap();
+    assert_eq!(res.bidder_open_order_count, 2u64);
+    assert_eq!(res.bidder_escrowed_offer, Uint128::from(900u128));
+
+    cancel_order_group(deps.as_mut(), mock_env(), mock_info(BIDDER, &[]), 1u64).unwrap();
+    let res = query_order_with_bidder_context(deps.as_ref(), 1u64).unwrap();
+    assert_eq!(res.bidder_open_order_count, 1u64);
+    assert_eq!(res.bidder_escrowed_offer, Uint128::from(300u128));
+}
+
+#[test]
+fn native_refund_dust_is_withheld_from_the_group_escrow() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            native_refund_dust: Some(Uint128::from(3u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one()).with_price(PAIR, uluna(), Decimal::one());
+    for _ in 0..2 {
+        submit_order_group(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+            &oracle,
+            Asset {
+                info: uluna(),
+                amount: Uint128::from(1000u128),
+            },
+            offer_token(),
+            vec![level(400, 350), level(500, 450)],
+            Uint128::from(10u128),
+        )
+        .unwrap();
+    }
+    let refund_msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: BIDDER.to_string(),
+        amount: vec![Coin::new(997u128, "uluna")],
+    });
+
+    // cancelling the group
+    let res = cancel_order_group(deps.as_mut(), mock_env(), mock_info(BIDDER, &[]), 1u64).unwrap();
+    assert_eq!(res.messages[0].msg, refund_msg);
+
+    // releasing the last order of the group
+    for order_id in [3u64, 4u64] {
+        let res = cancel_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &[]),
+            &oracle,
+            order_id,
+            None,
+            None,
+        )
+        .unwrap();
+        if order_id == 4u64 {
+            assert_eq!(res.messages[0].msg, refund_msg);
+        }
+    }
+    assert_eq!(
+        ACCRUED_NATIVE_DUST
+            .load(&deps.storage, "uluna".as_bytes())
+            .unwrap(),
+        Uint128::from(6u128)
+    );
+}
@@ -0,0 +1,3022 @@
+use std::str::FromStr;
+
+use cosmwasm_std::testing::{
+    mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
+use cosmwasm_std::{
+    attr, to_binary, Addr, BankMsg, Binary, Coin, ContractResult, CosmosMsg, Decimal, DepsMut,
+    OwnedDeps, QuerierWrapper, Reply, ReplyOn, Response, StdError, StdResult, SubMsg,
+    SubMsgExecutionResponse, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terraswap::asset::{Asset, AssetInfo, PairInfo};
+use terraswap::pair::{
+    Cw20HookMsg as PairCw20HookMsg, ExecuteMsg as PairExecuteMsg, SimulationResponse,
+};
+
+use crate::contract::{instantiate, reply};
+use crate::msg::{FillHook, InstantiateMsg, OrderGrid, RewardRamp, SignedOrder, SubmitOrderItem};
+use crate::order::{
+    cancel_order, cancel_user_pair_orders, claim_rebates, cleanup_expired_orders, execute_order,
+    execute_order_reply, fill_hook_reply, heartbeat, retarget_order, submit_order, submit_orders,
+    submit_signed_order, top_up_fee, update_order, CANCEL_ORDER_REPLY_ID, FILL_HOOK_REPLY_ID,
+};
+use crate::querier::PairOracle;
+use crate::state::{
+    read_order_counts_by_pair, OrderInfo, BLOCKED_ASSETS, CONFIG, LAST_HEARTBEAT, LAST_ORDER_ID,
+    ORDERS, PENDING_EXECUTION, PRICE_PRECISION, REBATES, TOTAL_OPEN_FEES,
+};
+use crate::testing::mock_api::MockKeyApi;
+use crate::testing::mock_oracle::MockOracle;
+use crate::testing::mock_querier::{mock_dependencies, WasmMockQuerier};
+use crate::testing::{
+    fill_order, instantiate_msg, mock_oracle, offer_token, submit_order_as, submit_token_order,
+    try_submit_token_order, uluna, BIDDER, EXECUTOR, FACTORY, FEE_TOKEN, OFFER_TOKEN, PAIR,
+};
+
+#[test]
+fn submit_order_against_mock_pair() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+
+    let res = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // the offer and the fee are pulled from the bidder
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: FEE_TOKEN.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: BIDDER.to_string(),
+                    recipient: MOCK_CONTRACT_ADDR.to_string(),
+                    amount: Uint128::from(10u128),
+                })
+                .unwrap(),
+            }),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: OFFER_TOKEN.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: BIDDER.to_string(),
+                    recipient: MOCK_CONTRACT_ADDR.to_string(),
+                    amount: Uint128::from(1000u128),
+                })
+                .unwrap(),
+            }),
+        ]
+    );
+
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.pair_addr.as_str(), PAIR);
+    assert_eq!(order.factory_addr.as_str(), FACTORY);
+    assert_eq!(order.offer_decimals, 6u8);
+    assert!(!order.order_hash.is_empty());
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::from(10u128)
+    );
+}
+
+#[test]
+fn submit_order_without_pair() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+
+    let err = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &MockOracle::default(),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("there is no terraswap pair for the 2 assets provided")
+    );
+}
+
+#[test]
+fn execute_order_settles_the_swapped_amount() {
+    // dust already held by the contract is not part of the return
+    let mut deps = mock_dependencies(&[Coin::new(50u128, "uluna")]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: OFFER_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: PAIR.to_string(),
+                amount: Uint128::from(1000u128),
+                msg: to_binary(&PairCw20HookMsg::Swap {
+                    to: Some(MOCK_CONTRACT_ADDR.to_string()),
+                    belief_price: None,
+                    max_spread: Some(Decimal::percent(5)),
+                })
+                .unwrap(),
+            })
+            .unwrap(),
+        })]
+    );
+
+    // the swap returned 1000 uluna
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1050u128, "uluna")]);
+    let res = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: BIDDER.to_string(),
+                amount: vec![Coin::new(900u128, "uluna")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: EXECUTOR.to_string(),
+                amount: vec![Coin::new(100u128, "uluna")],
+            }),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: FEE_TOKEN.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: EXECUTOR.to_string(),
+                    amount: Uint128::from(10u128),
+                })
+                .unwrap(),
+            }),
+        ]
+    );
+    assert!(ORDERS
+        .may_load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn execute_order_with_insufficient_return() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    let err = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &mock_oracle(Decimal::percent(80)),
+        1u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("insufficient return amount"));
+}
+
+#[test]
+fn execute_order_settles_the_swapped_token_amount() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .with_token_balances(&[(OFFER_TOKEN, &[(MOCK_CONTRACT_ADDR, 30u128)])]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = MockOracle::default()
+        .with_pair(FACTORY, PAIR, [offer_token(), uluna()])
+        .with_price(PAIR, uluna(), Decimal::one());
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // the swap returned 950 token0000, less than simulated
+    deps.querier
+        .with_token_balances(&[(OFFER_TOKEN, &[(MOCK_CONTRACT_ADDR, 980u128)])]);
+    let res = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: OFFER_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: BIDDER.to_string(),
+                amount: Uint128::from(900u128),
+            })
+            .unwrap(),
+        })
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: OFFER_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: EXECUTOR.to_string(),
+                amount: Uint128::from(50u128),
+            })
+            .unwrap(),
+        })
+    );
+}
+
+#[test]
+fn cancel_order_swaps_the_rebate_within_max_spread() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+    let oracle =
+        mock_oracle(Decimal::one()).with_pair(FACTORY, "pair0001", [fee_token, offer_token()]);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        Some(true),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: FEE_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "pair0001".to_string(),
+                amount: Uint128::from(10u128),
+                msg: to_binary(&PairCw20HookMsg::Swap {
+                    to: Some(BIDDER.to_string()),
+                    belief_price: None,
+                    max_spread: Some(CONFIG.load(&deps.storage).unwrap().max_spread),
+                })
+                .unwrap(),
+            })
+            .unwrap(),
+        })
+    );
+}
+
+#[test]
+fn cancel_order_reply_reports_the_order_of_a_failed_refund() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    // the refunds carry the id of their order
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        2u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert!(res
+        .messages
+        .iter()
+        .all(|msg| msg.id == CANCEL_ORDER_REPLY_ID + 2u64 && msg.reply_on == ReplyOn::Error));
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: res.messages[0].id,
+            result: ContractResult::Err("failed".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("refund of order 2 failed: failed")
+    );
+}
+
+#[test]
+fn execute_order_skips_the_mirror_order_of_a_blocked_asset() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        Some(OrderGrid {
+            rebuy_price: Decimal::one(),
+            rebuy_fee: Uint128::from(20u128),
+        }),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    BLOCKED_ASSETS
+        .save(&mut deps.storage, uluna().to_string(), &true)
+        .unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000u128, "uluna")]);
+    let res = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+    // the bidder is paid and refunded the rebuy fee instead
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: BIDDER.to_string(),
+            amount: vec![Coin::new(900u128, "uluna")],
+        })
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: FEE_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: BIDDER.to_string(),
+                amount: Uint128::from(20u128),
+            })
+            .unwrap(),
+        })
+    );
+    assert_eq!(LAST_ORDER_ID.load(&deps.storage).unwrap(), 1u64);
+}
+
+#[test]
+fn execute_order_resubmits_a_grid_order_as_its_mirror() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        Some(OrderGrid {
+            rebuy_price: Decimal::percent(120),
+            rebuy_fee: Uint128::from(20u128),
+        }),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // the proceeds stay escrowed in the mirrored order instead of being paid out
+    let res = fill_order(&mut deps, &oracle, 1u64, 1000);
+    assert!(res.attributes.contains(&attr("mirror_order_id", "2")));
+    // only the excess is paid out, to the executor
+    assert_eq!(
+        res.messages
+            .into_iter()
+            .map(|msg| msg.msg)
+            .filter(|msg| matches!(msg, CosmosMsg::Bank(_)))
+            .collect::<Vec<CosmosMsg>>(),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: EXECUTOR.to_string(),
+            amount: vec![Coin::new(100u128, "uluna")],
+        })]
+    );
+
+    let mirror_order: OrderInfo = ORDERS.load(&deps.storage, &2u64.to_be_bytes()).unwrap();
+    assert_eq!(
+        mirror_order.offer_asset,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        }
+    );
+    assert_eq!(
+        mirror_order.ask_asset,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1080u128),
+        }
+    );
+    assert_eq!(mirror_order.fee_amount, Uint128::from(20u128));
+    assert_eq!(mirror_order.grid, None);
+}
+
+#[test]
+fn execute_order_skips_a_mirror_order_overflowing_its_ask() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let offer_amount: u128 = 10_000_000_000_000_000_000u128;
+    let ask_amount: u128 = offer_amount / 10 * 9;
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(offer_amount),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(ask_amount),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        Some(OrderGrid {
+            rebuy_price: Decimal::from_str("100000000000000000000").unwrap(),
+            rebuy_fee: Uint128::from(20u128),
+        }),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // the reply pays the bidder instead of panicking on the rebuy ask
+    let res = fill_order(&mut deps, &oracle, 1u64, ask_amount);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: BIDDER.to_string(),
+            amount: vec![Coin::new(ask_amount, "uluna")],
+        })
+    );
+    assert_eq!(LAST_ORDER_ID.load(&deps.storage).unwrap(), 1u64);
+}
+
+#[test]
+fn total_open_fees_include_the_bond_and_rebuy_fee() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::from(5u128),
+        Some(OrderGrid {
+            rebuy_price: Decimal::one(),
+            rebuy_fee: Uint128::from(20u128),
+        }),
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::from(35u128)
+    );
+
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn top_up_fee_rehashes_the_order() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+
+    let config = CONFIG.load(&deps.storage).unwrap();
+    top_up_fee(
+        deps.as_mut(),
+        &config,
+        Addr::unchecked(FEE_TOKEN),
+        Addr::unchecked(BIDDER),
+        1u64,
+        Uint128::from(5u128),
+    )
+    .unwrap();
+    let topped_up: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(topped_up.fee_amount, Uint128::from(15u128));
+    assert_ne!(topped_up.order_hash, order.order_hash);
+    assert_eq!(topped_up.order_hash, topped_up.compute_hash());
+}
+
+#[test]
+fn update_order_rehashes_the_order() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+
+    update_order(
+        deps.as_mut(),
+        mock_info(BIDDER, &[]),
+        1u64,
+        Decimal::percent(95),
+    )
+    .unwrap();
+    let updated: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(updated.ask_asset.amount, Uint128::from(950u128));
+    assert_ne!(updated.order_hash, order.order_hash);
+    assert_eq!(updated.order_hash, updated.compute_hash());
+}
+
+#[test]
+fn update_order_rejects_an_overflowing_reserve_price() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let offer_amount: u128 = 100_000_000_000_000_000_000u128;
+    submit_token_order(
+        deps.as_mut(),
+        &mock_oracle(Decimal::one()),
+        offer_amount,
+        offer_amount / 10 * 9,
+        10,
+    );
+
+    let res = update_order(
+        deps.as_mut(),
+        mock_info(BIDDER, &[]),
+        1u64,
+        Decimal::from_str("100000000000000000000").unwrap(),
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "ask amount at the reserve price overflows"
+        ))
+    );
+}
+
+#[test]
+fn submit_order_records_the_creation_time() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.created_at, mock_env().block.time.seconds());
+}
+
+// the cw20 send of the offer executing order 1 into pair_addr
+fn execute_swap_msg(pair_addr: &str, max_spread: Decimal) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: OFFER_TOKEN.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Send {
+            contract: pair_addr.to_string(),
+            amount: Uint128::from(1000u128),
+            msg: to_binary(&PairCw20HookMsg::Swap {
+                to: Some(MOCK_CONTRACT_ADDR.to_string()),
+                belief_price: None,
+                max_spread: Some(max_spread),
+            })
+            .unwrap(),
+        })
+        .unwrap(),
+    })
+}
+
+#[test]
+fn execute_order_resolves_the_pair_again() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    // the factory now resolves the assets to a redeployed pair
+    let oracle = MockOracle::default()
+        .with_pair(FACTORY, "pair0002", [offer_token(), uluna()])
+        .with_price("pair0002", offer_token(), Decimal::one());
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        execute_swap_msg("pair0002", CONFIG.load(&deps.storage).unwrap().max_spread)
+    );
+
+    let err = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &MockOracle::default(),
+        1u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("there is no terraswap pair for the 2 assets provided")
+    );
+}
+
+#[test]
+fn heartbeat_records_the_keeper_time() {
+    let mut deps = mock_dependencies(&[]);
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(60u64);
+
+    let res = heartbeat(deps.as_mut(), env.clone(), mock_info(EXECUTOR, &[])).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "heartbeat"),
+            attr("executor", EXECUTOR),
+            attr("timestamp", env.block.time.seconds().to_string()),
+        ]
+    );
+    assert_eq!(
+        LAST_HEARTBEAT
+            .load(&deps.storage, EXECUTOR.as_bytes())
+            .unwrap(),
+        env.block.time.seconds()
+    );
+}
+
+// submits an order of BIDDER offering 1000 token0000 for 900 uluna with a fee of 100
+fn submit_expiring_order(deps: DepsMut, oracle: &MockOracle, expires_at: Option<u64>) {
+    submit_order(
+        deps,
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(100u128),
+        expires_at,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+fn token_transfer_msg(token: &str, recipient: &str, amount: u128) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        funds: vec![],
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount: Uint128::from(amount),
+        })
+        .unwrap(),
+    })
+}
+
+#[test]
+fn anyone_cleans_up_an_expired_order_for_a_reward() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            cleanup_reward_bps: Some(1000u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let expires_at: u64 = mock_env().block.time.seconds() + 100u64;
+    submit_expiring_order(
+        deps.as_mut(),
+        &mock_oracle(Decimal::one()),
+        Some(expires_at),
+    );
+
+    // a live order is skipped
+    let res = cleanup_expired_orders(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper0000", &[]),
+        vec![1u64],
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100u64);
+    let res = cleanup_expired_orders(deps.as_mut(), env, mock_info("keeper0000", &[]), vec![1u64])
+        .unwrap();
+    assert_eq!(
+        res.messages
+            .into_iter()
+            .map(|msg| msg.msg)
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            token_transfer_msg(OFFER_TOKEN, BIDDER, 1000),
+            token_transfer_msg(FEE_TOKEN, BIDDER, 90),
+            token_transfer_msg(FEE_TOKEN, "keeper0000", 10),
+        ]
+    );
+    assert!(ORDERS
+        .may_load(&deps.storage, &1u64.to_be_bytes())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn execute_order_caps_the_swap_spread() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            max_spread: Some(Decimal::percent(2)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        execute_swap_msg(PAIR, Decimal::percent(2))
+    );
+}
+
+#[test]
+fn execute_order_defaults_the_swap_spread() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        execute_swap_msg(PAIR, Decimal::percent(5))
+    );
+}
+
+#[test]
+fn effective_fee_grows_with_the_order_age() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            fee_bump_period: Some(1000u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::from(100u128),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    let created_at: u64 = order.created_at;
+    assert_eq!(
+        order.effective_fee(created_at, 1000u64),
+        Uint128::from(10u128)
+    );
+    assert_eq!(
+        order.effective_fee(created_at + 500u64, 1000u64),
+        Uint128::from(60u128)
+    );
+    assert_eq!(
+        order.effective_fee(created_at + 2000u64, 1000u64),
+        Uint128::from(110u128)
+    );
+}
+
+#[test]
+fn implied_price_is_normalized_by_the_decimals() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_decimals("token0001", 18u8);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let token = AssetInfo::Token {
+        contract_addr: "token0001".to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [token.clone(), uluna()])
+        .with_price(
+            "pair0001",
+            token.clone(),
+            Decimal::from_ratio(1u128, 100_000u128),
+        );
+
+    // 1 token0000 for 2 luna, then 1 token0001 for 3 luna
+    submit_token_order(deps.as_mut(), &oracle, 1_000_000, 2_000_000, 10);
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: token,
+            amount: Uint128::from(1_000_000_000_000_000_000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(3_000_000u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let order1: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    let order2: OrderInfo = ORDERS.load(&deps.storage, &2u64.to_be_bytes()).unwrap();
+    assert_eq!(order2.offer_decimals, 18u8);
+    assert_eq!(
+        order1.implied_price(),
+        Uint128::new(2u128 * PRICE_PRECISION)
+    );
+    assert_eq!(
+        order2.implied_price(),
+        Uint128::new(3u128 * PRICE_PRECISION)
+    );
+    assert!(order2.implied_price() > order1.implied_price());
+}
+
+#[test]
+fn failed_fill_leaves_the_order_open() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    let res = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Err("max spread assertion".to_string()),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "fill_failed"),
+            attr("order_id", "1"),
+            attr("reason", "max spread assertion"),
+        ]
+    );
+    assert!(res.messages.is_empty());
+
+    assert_eq!(
+        ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap(),
+        order
+    );
+    assert_eq!(PENDING_EXECUTION.may_load(&deps.storage).unwrap(), None);
+    assert_eq!(
+        TOTAL_OPEN_FEES.load(&deps.storage).unwrap(),
+        Uint128::from(10u128)
+    );
+}
+
+#[test]
+fn cancel_user_pair_orders_only_cancels_the_sender_orders_on_the_pair() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let token = AssetInfo::Token {
+        contract_addr: "token0001".to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [token.clone(), uluna()])
+        .with_price("pair0001", token, Decimal::one());
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0001");
+    submit_order_as(deps.as_mut(), &oracle, "addr0001", OFFER_TOKEN);
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+
+    let res = cancel_user_pair_orders(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        PAIR.to_string(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "cancel_user_pair_orders"),
+            attr("pair_addr", PAIR),
+            attr("order_ids", "1,4"),
+            attr("refunded_fee", "20fee0000"),
+        ]
+    );
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            token_transfer_msg(OFFER_TOKEN, BIDDER, 1000),
+            token_transfer_msg(OFFER_TOKEN, BIDDER, 1000),
+            token_transfer_msg(FEE_TOKEN, BIDDER, 20),
+        ]
+    );
+
+    let order_ids: Vec<u64> = (1u64..=4u64)
+        .filter(|order_id| ORDERS.has(&deps.storage, &order_id.to_be_bytes()))
+        .collect();
+    assert_eq!(order_ids, vec![2u64, 3u64]);
+}
+
+#[test]
+fn execute_order_swaps_a_native_offer_to_the_contract() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one()).with_price(PAIR, uluna(), Decimal::one());
+    let offer_asset = Asset {
+        info: uluna(),
+        amount: Uint128::from(1000u128),
+    };
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        offer_asset.clone(),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: PAIR.to_string(),
+            funds: vec![Coin::new(1000u128, "uluna")],
+            msg: to_binary(&PairExecuteMsg::Swap {
+                offer_asset,
+                belief_price: None,
+                max_spread: Some(Decimal::percent(5)),
+                to: Some(MOCK_CONTRACT_ADDR.to_string()),
+            })
+            .unwrap(),
+        })
+    );
+}
+
+// a factory resolving any assets to the same pair
+struct FixedPairOracle(PairInfo);
+
+impl PairOracle for FixedPairOracle {
+    fn query_pair_info(
+        &self,
+        _querier: &QuerierWrapper,
+        _factory_addr: Addr,
+        _asset_infos: &[AssetInfo; 2],
+    ) -> StdResult<PairInfo> {
+        Ok(self.0.clone())
+    }
+
+    fn simulate(
+        &self,
+        _querier: &QuerierWrapper,
+        _pair_addr: Addr,
+        offer_asset: &Asset,
+    ) -> StdResult<SimulationResponse> {
+        Ok(SimulationResponse {
+            return_amount: offer_asset.amount,
+            spread_amount: Uint128::zero(),
+            commission_amount: Uint128::zero(),
+        })
+    }
+}
+
+#[test]
+fn submit_order_rejects_a_pair_not_trading_both_assets() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = FixedPairOracle(PairInfo {
+        asset_infos: [
+            offer_token(),
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+        ],
+        contract_addr: PAIR.to_string(),
+        liquidity_token: "pair0000_lp".to_string(),
+    });
+
+    let err = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("pair pair0000 does not trade uluna")
+    );
+}
+
+#[test]
+fn submit_order_applies_the_default_ttl() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            default_order_ttl_seconds: Some(3600u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let now: u64 = mock_env().block.time.seconds();
+    submit_expiring_order(deps.as_mut(), &oracle, None);
+    submit_expiring_order(deps.as_mut(), &oracle, Some(now + 60u64));
+
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.expires_at, Some(now + 3600u64));
+    let order: OrderInfo = ORDERS.load(&deps.storage, &2u64.to_be_bytes()).unwrap();
+    assert_eq!(order.expires_at, Some(now + 60u64));
+}
+
+#[test]
+fn fee_token_offer_is_escrowed_and_refunded_with_the_fee() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [fee_token.clone(), uluna()])
+        .with_price("pair0001", fee_token.clone(), Decimal::one());
+
+    let res = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: fee_token,
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: FEE_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: BIDDER.to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(1010u128),
+            })
+            .unwrap(),
+        })]
+    );
+
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![token_transfer_msg(FEE_TOKEN, BIDDER, 1010)]
+    );
+}
+
+#[test]
+fn cancel_order_reports_the_time_to_expiry() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let now: u64 = mock_env().block.time.seconds();
+    submit_expiring_order(deps.as_mut(), &oracle, Some(now + 600u64));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100u64);
+    let res = cancel_order(
+        deps.as_mut(),
+        env,
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("was_expired", "false")));
+    assert!(res.attributes.contains(&attr("seconds_to_expiry", "500")));
+}
+
+#[test]
+fn execute_order_reply_rejects_a_short_return_without_underflow() {
+    let mut deps = mock_dependencies(&[Coin::new(500u128, "uluna")]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    let settle = |deps: DepsMut| {
+        execute_order(
+            deps,
+            mock_env(),
+            mock_info(EXECUTOR, &[]),
+            &oracle,
+            1u64,
+            None,
+            None,
+        )
+        .unwrap();
+    };
+
+    // the swap returned less than the ask amount
+    settle(deps.as_mut());
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1300u128, "uluna")]);
+    let err = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("insufficient return amount"));
+
+    // the balance of the contract dropped below the balance before the swap
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(500u128, "uluna")]);
+    settle(deps.as_mut());
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(100u128, "uluna")]);
+    let err = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(err, StdError::Overflow { .. }));
+}
+
+#[test]
+fn offer_fee_is_taken_out_of_the_offer() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            allow_offer_fee: Some(true),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    let res = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::zero(),
+        None,
+        Uint128::zero(),
+        None,
+        Some(100u64),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    // only the offer is escrowed, inclusive of the fee
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: OFFER_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: BIDDER.to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(1000u128),
+            })
+            .unwrap(),
+        })]
+    );
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.offer_asset.amount, Uint128::from(990u128));
+    assert_eq!(order.offer_fee, Uint128::from(10u128));
+
+    // the executor is paid the fee in the offer asset
+    let res = fill_order(&mut deps, &oracle, 1u64, 990);
+    assert!(res
+        .messages
+        .iter()
+        .any(|msg| msg.msg == token_transfer_msg(OFFER_TOKEN, EXECUTOR, 10)));
+}
+
+#[test]
+fn offer_fee_must_be_allowed() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+
+    let err = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::zero(),
+        None,
+        Uint128::zero(),
+        None,
+        Some(100u64),
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("offer fees are not allowed"));
+}
+
+#[test]
+fn dust_excess_is_folded_into_the_bidder_payout() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            dust_threshold: Some(Uint128::from(50u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = fill_order(&mut deps, &oracle, 1u64, 920);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: BIDDER.to_string(),
+            amount: vec![Coin::new(920u128, "uluna")],
+        })
+    );
+    assert!(res.attributes.contains(&attr("excess_amount", "0")));
+    assert!(!res.messages.iter().any(|msg| matches!(
+        &msg.msg,
+        CosmosMsg::Bank(BankMsg::Send { to_address, .. }) if to_address == EXECUTOR
+    )));
+}
+
+#[test]
+fn messages_count_matches_the_dispatched_messages() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("messages_count", "1")));
+    assert_eq!(res.messages.len(), 1usize);
+
+    // bidder payout, excess and fee
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000u128, "uluna")]);
+    let res = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("messages_count", "3")));
+    assert_eq!(res.messages.len(), 3usize);
+}
+
+#[test]
+fn executor_proceeds_route_to_the_executor_recipient() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        Some("keeper0000".to_string()),
+        None,
+    )
+    .unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000u128, "uluna")]);
+    let res = execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+
+    // the bidder is still paid its ask, the excess and fee go to the recipient
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: BIDDER.to_string(),
+                amount: vec![Coin::new(900u128, "uluna")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "keeper0000".to_string(),
+                amount: vec![Coin::new(100u128, "uluna")],
+            }),
+            token_transfer_msg(FEE_TOKEN, "keeper0000", 10),
+        ]
+    );
+    assert!(res.attributes.contains(&attr("executor", EXECUTOR)));
+    assert!(res.attributes.contains(&attr("recipient", "keeper0000")));
+}
+
+#[test]
+fn cancel_order_swaps_the_refund_into_the_refund_asset() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let oracle =
+        mock_oracle(Decimal::one()).with_pair(FACTORY, "pair0003", [offer_token(), uusd.clone()]);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        Some(uusd),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: OFFER_TOKEN.to_string(),
+                funds: vec![],
+                msg: to_binary(&Cw20ExecuteMsg::Send {
+                    contract: "pair0003".to_string(),
+                    amount: Uint128::from(1000u128),
+                    msg: to_binary(&PairCw20HookMsg::Swap {
+                        to: Some(BIDDER.to_string()),
+                        belief_price: None,
+                        max_spread: Some(Decimal::percent(5)),
+                    })
+                    .unwrap(),
+                })
+                .unwrap(),
+            }),
+            token_transfer_msg(FEE_TOKEN, BIDDER, 10),
+        ]
+    );
+
+    // without a pair the offer is refunded as is
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        2u64,
+        None,
+        Some(AssetInfo::NativeToken {
+            denom: "ukrw".to_string(),
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            token_transfer_msg(OFFER_TOKEN, BIDDER, 1000),
+            token_transfer_msg(FEE_TOKEN, BIDDER, 10),
+        ]
+    );
+}
+
+#[test]
+fn retarget_order_moves_the_order_to_the_new_pair() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    let err = retarget_order(
+        deps.as_mut(),
+        mock_info(BIDDER, &[]),
+        &mock_oracle(Decimal::one()),
+        1u64,
+        offer_token(),
+        uluna(),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("order already targets the pair"));
+
+    // the factory now resolves the assets to a new pair
+    let oracle = MockOracle::default()
+        .with_pair(FACTORY, "pair0002", [offer_token(), uluna()])
+        .with_price("pair0002", offer_token(), Decimal::one());
+    let err = retarget_order(
+        deps.as_mut(),
+        mock_info("addr0001", &[]),
+        &oracle,
+        1u64,
+        offer_token(),
+        uluna(),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+    let err = retarget_order(
+        deps.as_mut(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        uluna(),
+        offer_token(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("assets do not match the order assets")
+    );
+
+    let res = retarget_order(
+        deps.as_mut(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        offer_token(),
+        uluna(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "retarget_order"),
+            attr("order_id", "1"),
+            attr("old_pair_addr", PAIR),
+            attr("pair_addr", "pair0002"),
+        ]
+    );
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.pair_addr, Addr::unchecked("pair0002"));
+    assert_eq!(
+        read_order_counts_by_pair(&deps.storage, None, None).unwrap(),
+        vec![("pair0002".to_string(), 1u64)]
+    );
+}
+
+// an item of a batch asking 900 of the other asset of the pair
+fn submit_order_item(offer_asset: Asset) -> SubmitOrderItem {
+    let ask_asset_info: AssetInfo = if offer_asset.info == uluna() {
+        offer_token()
+    } else {
+        uluna()
+    };
+
+    SubmitOrderItem {
+        offer_asset,
+        ask_asset: Asset {
+            info: ask_asset_info,
+            amount: Uint128::from(900u128),
+        },
+        fee_amount: Uint128::from(10u128),
+        expires_at: None,
+        fee_bond: None,
+        grid: None,
+        offer_fee_bps: None,
+        reward_ramp: None,
+        fill_hook: None,
+        max_spread_bps: None,
+    }
+}
+
+#[test]
+fn submit_orders_escrows_the_batch_at_once() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one()).with_price(PAIR, uluna(), Decimal::one());
+    let items: Vec<SubmitOrderItem> = vec![
+        submit_order_item(Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        }),
+        submit_order_item(Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        }),
+        submit_order_item(Asset {
+            info: offer_token(),
+            amount: Uint128::from(1500u128),
+        }),
+    ];
+
+    let err = submit_orders(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(500u128, "uluna")]),
+        &oracle,
+        items.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, StdError::GenericErr { .. }));
+
+    let res = submit_orders(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        items,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[2], attr("order_ids", "1,2,3"));
+    let transfer_from_msg = |token: &str, amount: u128| {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: BIDDER.to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(amount),
+            })
+            .unwrap(),
+        })
+    };
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            transfer_from_msg(FEE_TOKEN, 30),
+            transfer_from_msg(OFFER_TOKEN, 2500),
+        ]
+    );
+    assert_eq!(LAST_ORDER_ID.load(&deps.storage).unwrap(), 3u64);
+
+    let err = submit_orders(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        vec![],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("must submit between 1 and 10 orders")
+    );
+}
+
+#[test]
+fn reward_ramp_raises_the_fee_with_the_order_age() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let res = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::zero(),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        Some(RewardRamp {
+            start_fee: Uint128::from(10u128),
+            end_fee: Uint128::from(110u128),
+            ramp_seconds: 100u64,
+        }),
+        None,
+        None,
+    )
+    .unwrap();
+    // the end fee is escrowed upfront
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: FEE_TOKEN.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: BIDDER.to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(110u128),
+            })
+            .unwrap(),
+        })
+    );
+
+    // the ramp period overrides the fee bump period
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    let created_at: u64 = order.created_at;
+    for (age, fee) in [(0u64, 10u128), (25, 35), (50, 60), (100, 110), (200, 110)] {
+        assert_eq!(
+            order.effective_fee(created_at + age, 0u64),
+            Uint128::from(fee)
+        );
+    }
+
+    // filled halfway, the unused half of the ramp is refunded
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(50u64);
+    execute_order(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(900u128, "uluna")]);
+    let res = execute_order_reply(
+        deps.as_mut(),
+        env,
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("fee_amount", "60")));
+    assert!(res.attributes.contains(&attr("refunded_bond", "50")));
+    assert!(res
+        .messages
+        .iter()
+        .any(|msg| msg.msg == token_transfer_msg(FEE_TOKEN, BIDDER, 50)));
+}
+
+#[test]
+fn executor_fee_cap_resets_every_epoch() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            max_fee_per_epoch: Some(Uint128::from(25u128)),
+            epoch_seconds: Some(3600u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..4 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    fill_order(&mut deps, &oracle, 1u64, 1000);
+    fill_order(&mut deps, &oracle, 2u64, 2000);
+
+    // a third fee of 10 would cross the cap of 25 within the epoch
+    let err = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        3u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("executor fee cap of 25 per epoch reached")
+    );
+
+    // other executors have their own cap
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("executor0001", &[]),
+        &oracle,
+        3u64,
+        None,
+        None,
+    )
+    .unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(3000u128, "uluna")]);
+    execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+    .unwrap();
+
+    // the next epoch starts from zero
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3600u64);
+    execute_order(
+        deps.as_mut(),
+        env,
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        4u64,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+// order 1000 token0000 for 900 uluna, signed with nonce 1 by the private key sha256("addr0000")
+fn signed_order(nonce: u64) -> SignedOrder {
+    SignedOrder {
+        offer_asset: Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        ask_asset: Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        fee_amount: Uint128::from(10u128),
+        expires_at: None,
+        nonce,
+    }
+}
+
+#[test]
+fn submit_signed_order_verifies_the_bidder_signature() {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockKeyApi::default(),
+        querier: WasmMockQuerier::new(MockQuerier::new(&[])),
+    };
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let signature = Binary::from(
+        hex::decode(
+            "e37bbe7b21c3b4495764710adebe9e5eabe5dc6e816244c927833825073533963e439287ff6ee60d88104ccf4cc2d7cb5923c562a5974d218f80293d4dd96639",
+        )
+        .unwrap(),
+    );
+    let pub_key = Binary::from(
+        hex::decode("0283724be801ef4ec72e0f129307a64b8769deb2213bbccbeb5c6391336d989417").unwrap(),
+    );
+    // hex of ripemd160(sha256(pub_key))
+    let bidder = "8180658c8faf2aa4152e0ef49ee8821787d98bed";
+
+    let err = submit_signed_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer0000", &[]),
+        &oracle,
+        signed_order(2u64),
+        signature.clone(),
+        pub_key.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("invalid signature"));
+
+    let res = submit_signed_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer0000", &[]),
+        &oracle,
+        signed_order(1u64),
+        signature.clone(),
+        pub_key.clone(),
+    )
+    .unwrap();
+    let transfer_from_msg = |token: &str, amount: u128| {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: bidder.to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(amount),
+            })
+            .unwrap(),
+        })
+    };
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|msg| msg.msg.clone())
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            transfer_from_msg(FEE_TOKEN, 10),
+            transfer_from_msg(OFFER_TOKEN, 1000),
+        ]
+    );
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.bidder_addr, Addr::unchecked(bidder));
+
+    // a signature is accepted once
+    let err = submit_signed_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer0000", &[]),
+        &oracle,
+        signed_order(1u64),
+        signature,
+        pub_key,
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("signed order already submitted"));
+}
+
+#[test]
+fn rebates_accrue_across_fills_and_are_claimed_once() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            bidder_rebate_bps: Some(1000u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 20);
+
+    // the executor is paid the fee less the rebate, no rebate is transferred per fill
+    let res = fill_order(&mut deps, &oracle, 1u64, 900);
+    assert!(res.attributes.contains(&attr("rebate_amount", "1")));
+    assert!(res
+        .messages
+        .iter()
+        .any(|msg| msg.msg == token_transfer_msg(FEE_TOKEN, EXECUTOR, 9)));
+    assert!(!res
+        .messages
+        .iter()
+        .any(|msg| msg.msg == token_transfer_msg(FEE_TOKEN, BIDDER, 1)));
+    fill_order(&mut deps, &oracle, 2u64, 1800);
+    assert_eq!(
+        REBATES.load(&deps.storage, BIDDER.as_bytes()).unwrap(),
+        Uint128::from(3u128)
+    );
+
+    let res = claim_rebates(deps.as_mut(), mock_info(BIDDER, &[])).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        token_transfer_msg(FEE_TOKEN, BIDDER, 3)
+    );
+    let err = claim_rebates(deps.as_mut(), mock_info(BIDDER, &[])).unwrap_err();
+    assert_eq!(err, StdError::generic_err("no rebates to claim"));
+}
+
+#[test]
+fn execute_order_simulates_the_order_direction_of_a_reversed_pair() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    // the pair stores its assets as (ask, offer) and the reverse direction is worth half
+    let oracle = MockOracle::default()
+        .with_pair(FACTORY, PAIR, [uluna(), offer_token()])
+        .with_price(PAIR, offer_token(), Decimal::one())
+        .with_price(PAIR, uluna(), Decimal::percent(50));
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        execute_swap_msg(PAIR, Decimal::percent(5))
+    );
+    PENDING_EXECUTION.remove(&mut deps.storage);
+
+    // a pair resolved at execution must still trade both assets
+    let oracle = FixedPairOracle(PairInfo {
+        asset_infos: [
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            offer_token(),
+        ],
+        contract_addr: PAIR.to_string(),
+        liquidity_token: "pair0000_lp".to_string(),
+    });
+    let err = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        2u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("pair pair0000 does not trade uluna")
+    );
+}
+
+#[test]
+fn settle_order_notifies_the_fill_hook() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    let err = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        Some(FillHook {
+            contract: "".to_string(),
+            msg: Binary::from(b"{}".to_vec()),
+        }),
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(err, StdError::GenericErr { .. }));
+
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        Some(FillHook {
+            contract: "hook0000".to_string(),
+            msg: Binary::from(b"{\"filled\":{}}".to_vec()),
+        }),
+        None,
+    )
+    .unwrap();
+
+    let res = fill_order(&mut deps, &oracle, 1u64, 950);
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "hook0000".to_string(),
+                funds: vec![],
+                msg: Binary::from(b"{\"filled\":{}}".to_vec()),
+            }),
+            FILL_HOOK_REPLY_ID,
+        )
+    );
+
+    // a failing hook keeps the fill and reports the reason
+    let res = fill_hook_reply(ContractResult::Err("hook failed".to_string())).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "fill_hook_failed"),
+            attr("reason", "hook failed"),
+        ]
+    );
+    assert!(fill_hook_reply(ContractResult::Ok(SubMsgExecutionResponse {
+        events: vec![],
+        data: None,
+    }))
+    .unwrap()
+    .attributes
+    .is_empty());
+}
+
+#[test]
+fn execute_order_rejects_orders_older_than_the_max_age() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            max_order_age_seconds: Some(100u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101u64);
+    let err = execute_order(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("order is older than the max order age")
+    );
+
+    // the order is still executable at exactly its max age
+    env.block.time = mock_env().block.time.plus_seconds(100u64);
+    execute_order(
+        deps.as_mut(),
+        env,
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn exact_fill_sends_no_zero_amount_transfer() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            protocol_fee_bps: Some(10000u64),
+            treasury: Some("treasury0000".to_string()),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::percent(90));
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    // the swap returns exactly the ask amount and the protocol takes the whole fee
+    let res = fill_order(&mut deps, &oracle, 1u64, 900);
+    let messages: Vec<CosmosMsg> = res.messages.into_iter().map(|m| m.msg).collect();
+    assert_eq!(messages.len(), 2);
+    assert!(messages.contains(&CosmosMsg::Bank(BankMsg::Send {
+        to_address: BIDDER.to_string(),
+        amount: vec![Coin::new(900u128, "uluna")],
+    })));
+    assert!(messages.contains(&token_transfer_msg(FEE_TOKEN, "treasury0000", 10)));
+    assert!(res.attributes.contains(&attr("excess_amount", "0")));
+}
+
+#[test]
+fn orders_resolve_their_pair_from_the_fallback_factory() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    // only the fallback factory lists the pair
+    let oracle = MockOracle::default()
+        .with_pair("factory0001", "pair0001", [offer_token(), uluna()])
+        .with_price("pair0001", offer_token(), Decimal::one());
+
+    let err = try_submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("there is no terraswap pair for the 2 assets provided")
+    );
+
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            fallback_factory: Some("factory0001".to_string()),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.factory_addr, Addr::unchecked("factory0001"));
+    assert_eq!(order.pair_addr, Addr::unchecked("pair0001"));
+
+    // the order executes against the factory it was resolved from
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        execute_swap_msg("pair0001", Decimal::percent(5))
+    );
+}
+
+#[test]
+fn cleanup_expired_orders_in_a_batch_pays_the_reward_once() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            cleanup_reward_bps: Some(1000u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let expires_at: u64 = mock_env().block.time.seconds() + 100u64;
+    submit_expiring_order(deps.as_mut(), &oracle, Some(expires_at));
+    submit_expiring_order(deps.as_mut(), &oracle, None);
+    submit_expiring_order(deps.as_mut(), &oracle, Some(expires_at));
+
+    let err = cleanup_expired_orders(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper0000", &[]),
+        (1u64..=31u64).collect(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("cannot clean up more than 30 orders at once")
+    );
+
+    // the live order 2 and the unknown order 9 are skipped
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100u64);
+    let res = cleanup_expired_orders(
+        deps.as_mut(),
+        env,
+        mock_info("keeper0000", &[]),
+        vec![1u64, 2u64, 9u64, 3u64],
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .into_iter()
+            .map(|msg| msg.msg)
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            token_transfer_msg(OFFER_TOKEN, BIDDER, 1000),
+            token_transfer_msg(FEE_TOKEN, BIDDER, 90),
+            token_transfer_msg(OFFER_TOKEN, BIDDER, 1000),
+            token_transfer_msg(FEE_TOKEN, BIDDER, 90),
+            token_transfer_msg(FEE_TOKEN, "keeper0000", 20),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "cleanup_expired_orders"),
+            attr("order_ids", "1,3"),
+            attr("cleanup_reward", "20fee0000"),
+        ]
+    );
+    assert!(ORDERS
+        .may_load(&deps.storage, &2u64.to_be_bytes())
+        .unwrap()
+        .is_some());
+}
+
+fn submit_order_with_max_spread(
+    deps: DepsMut,
+    oracle: &MockOracle,
+    max_spread_bps: u16,
+) -> StdResult<Response> {
+    submit_order(
+        deps,
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        oracle,
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        Some(max_spread_bps),
+    )
+}
+
+#[test]
+fn execute_order_uses_the_max_spread_of_the_order() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    let err = submit_order_with_max_spread(deps.as_mut(), &oracle, 10001u16).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("max_spread_bps must not exceed 10000")
+    );
+
+    submit_order_with_max_spread(deps.as_mut(), &oracle, 100u16).unwrap();
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    assert_eq!(order.max_spread, Some(Decimal::percent(1)));
+
+    // the order overrides the 5% of the config
+    let res = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        execute_swap_msg(PAIR, Decimal::percent(1))
+    );
+}
+
+#[test]
+fn fee_token_offer_overflowing_with_the_fee_is_rejected() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [fee_token.clone(), uluna()])
+        .with_price("pair0001", fee_token.clone(), Decimal::one());
+
+    let err = submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        Asset {
+            info: fee_token,
+            amount: Uint128::MAX,
+        },
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("combined offer and fee amount overflows")
+    );
+}
+
+fn execute_with_min_return(
+    deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+    oracle: &MockOracle,
+    ask_balance_after: u128,
+) -> StdResult<Response> {
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        oracle,
+        1u64,
+        None,
+        Some(Uint128::from(950u128)),
+    )?;
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![Coin::new(ask_balance_after, "uluna")],
+    );
+
+    execute_order_reply(
+        deps.as_mut(),
+        mock_env(),
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        }),
+    )
+}
+
+#[test]
+fn execute_order_checks_the_return_against_min_return() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    // the on-chain simulation is skipped, the actual return is checked in the reply
+    let oracle = mock_oracle(Decimal::percent(50));
+    let err = execute_with_min_return(&mut deps, &oracle, 940).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("return amount is less than min_return")
+    );
+
+    // the failed reply reverts the swap with it
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(0u128, "uluna")]);
+    let res = execute_with_min_return(&mut deps, &oracle, 960).unwrap();
+    assert!(res.attributes.contains(&attr("excess_amount", "60")));
+}
+
+#[test]
+fn submit_order_requires_the_min_notional() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            notional_asset_info: Some(uluna()),
+            min_notional: Some(Uint128::from(500u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    // valued at par in the notional asset
+    let err = try_submit_token_order(deps.as_mut(), &oracle, 499, 450, 10).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("offer notional should be greater than 500")
+    );
+    try_submit_token_order(deps.as_mut(), &oracle, 500, 450, 10).unwrap();
+    try_submit_token_order(deps.as_mut(), &oracle, 501, 450, 10).unwrap();
+    assert_eq!(LAST_ORDER_ID.load(&deps.storage).unwrap(), 2u64);
+}
+
+#[test]
+fn submit_order_requires_a_higher_dynamic_fee_in_a_shallow_pool() {
+    // the min fee required of a 1000 offer into a pool holding the reserve
+    let required_min_fee = |reserve: u128| -> StdError {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_pool(
+            PAIR,
+            [
+                Asset {
+                    info: offer_token(),
+                    amount: Uint128::from(reserve),
+                },
+                Asset {
+                    info: uluna(),
+                    amount: Uint128::from(reserve),
+                },
+            ],
+        );
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                dynamic_fee: Some(true),
+                ..instantiate_msg()
+            },
+        )
+        .unwrap();
+        try_submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 1)
+            .unwrap_err()
+    };
+
+    // 10% of a shallow pool raises the min fee of 5 by 50, 1% of a deep pool by 5
+    assert_eq!(
+        required_min_fee(10000u128),
+        StdError::generic_err("fee should be greater than 55")
+    );
+    assert_eq!(
+        required_min_fee(100000u128),
+        StdError::generic_err("fee should be greater than 10")
+    );
+}
@@ -0,0 +1,561 @@
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    attr, to_binary, BankMsg, Coin, CosmosMsg, Decimal, Deps, StdError, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use terraswap::asset::Asset;
+use terraswap::pair::ExecuteMsg as PairExecuteMsg;
+
+use crate::contract::instantiate;
+use crate::msg::InstantiateMsg;
+use crate::order::{cancel_order, execute_order, submit_order};
+use crate::owner::{
+    block_asset, deposit_bounty, deposit_incentive, evacuate_asset, force_cancel_order, pause_pair,
+    set_bounty_per_fill, set_pair_min_fee, sweep_native_dust, unblock_asset, unpause_pair,
+};
+use crate::query::query_pause_state;
+use crate::state::{ACCRUED_NATIVE_DUST, BOUNTY_POOL, INCENTIVE_POOL, TOTAL_OPEN_ORDERS};
+use crate::testing::mock_querier::mock_dependencies;
+use crate::testing::{
+    fill_order, instantiate_msg, mock_oracle, offer_token, submit_token_order,
+    try_submit_token_order, uluna, BIDDER, EXECUTOR, PAIR,
+};
+
+#[test]
+fn evacuate_asset_pages_through_both_sides() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 950, 10);
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    block_asset(deps.as_mut(), mock_info("owner", &[]), uluna()).unwrap();
+
+    // the orders offering the asset come first, then the orders asking it
+    let res = evacuate_asset(deps.as_mut(), mock_info("owner", &[]), uluna(), Some(2u32)).unwrap();
+    assert!(res.attributes.contains(&attr("order_ids", "3,1")));
+    let res = evacuate_asset(deps.as_mut(), mock_info("owner", &[]), uluna(), Some(2u32)).unwrap();
+    assert!(res.attributes.contains(&attr("order_ids", "2")));
+    assert_eq!(TOTAL_OPEN_ORDERS.load(&deps.storage).unwrap(), 0u64);
+}
+
+#[test]
+fn blocked_assets_cannot_be_submitted() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    let err = block_asset(deps.as_mut(), mock_info(BIDDER, &[]), uluna()).unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+
+    block_asset(deps.as_mut(), mock_info("owner", &[]), uluna()).unwrap();
+    let err = try_submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10).unwrap_err();
+    assert_eq!(err, StdError::generic_err("asset uluna is blocked"));
+
+    unblock_asset(deps.as_mut(), mock_info("owner", &[]), uluna()).unwrap();
+    try_submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10).unwrap();
+}
+
+#[test]
+fn paused_pair_blocks_submit_and_execute_but_not_cancel() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let err = pause_pair(deps.as_mut(), mock_info(BIDDER, &[]), PAIR.to_string()).unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+    pause_pair(deps.as_mut(), mock_info("owner", &[]), PAIR.to_string()).unwrap();
+
+    let paused = StdError::generic_err("pair pair0000 is paused");
+    let err = try_submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10).unwrap_err();
+    assert_eq!(err, paused);
+    let err = execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(err, paused);
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    unpause_pair(deps.as_mut(), mock_info("owner", &[]), PAIR.to_string()).unwrap();
+    execute_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(EXECUTOR, &[]),
+        &oracle,
+        2u64,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn pair_min_fee_overrides_the_global_min_fee() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    set_pair_min_fee(
+        deps.as_mut(),
+        mock_info("owner", &[]),
+        PAIR.to_string(),
+        Some(Uint128::from(50u128)),
+    )
+    .unwrap();
+    let err = try_submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10).unwrap_err();
+    assert_eq!(err, StdError::generic_err("fee should be greater than 50"));
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 50);
+
+    // unset, the pair falls back to the global min fee
+    let res = set_pair_min_fee(
+        deps.as_mut(),
+        mock_info("owner", &[]),
+        PAIR.to_string(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(res.attributes[2], attr("min_fee_amount", "5"));
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    let err = try_submit_token_order(deps.as_mut(), &oracle, 1000, 900, 4).unwrap_err();
+    assert_eq!(err, StdError::generic_err("fee should be greater than 5"));
+}
+
+#[test]
+fn pause_state_reports_each_flag() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let pause_state = |deps: Deps| {
+        let res = query_pause_state(
+            deps,
+            Some(PAIR.to_string()),
+            Some(offer_token()),
+            Some(uluna()),
+        )
+        .unwrap();
+        (res.pair_paused, res.blocked_offer, res.blocked_ask)
+    };
+    assert_eq!(pause_state(deps.as_ref()), (false, false, false));
+
+    pause_pair(deps.as_mut(), mock_info("owner", &[]), PAIR.to_string()).unwrap();
+    assert_eq!(pause_state(deps.as_ref()), (true, false, false));
+
+    block_asset(deps.as_mut(), mock_info("owner", &[]), offer_token()).unwrap();
+    assert_eq!(pause_state(deps.as_ref()), (true, true, false));
+
+    unpause_pair(deps.as_mut(), mock_info("owner", &[]), PAIR.to_string()).unwrap();
+    unblock_asset(deps.as_mut(), mock_info("owner", &[]), offer_token()).unwrap();
+    block_asset(deps.as_mut(), mock_info("owner", &[]), uluna()).unwrap();
+    assert_eq!(pause_state(deps.as_ref()), (false, false, true));
+
+    // without a pair or assets nothing is reported
+    let res = query_pause_state(deps.as_ref(), None, None, None).unwrap();
+    assert!(!res.pair_paused && !res.blocked_offer && !res.blocked_ask);
+}
+
+#[test]
+fn bounty_is_paid_until_the_pool_is_drained() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..3 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+
+    let err = deposit_bounty(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        Uint128::from(25u128),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+    deposit_bounty(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        Uint128::from(25u128),
+    )
+    .unwrap();
+    set_bounty_per_fill(
+        deps.as_mut(),
+        mock_info("owner", &[]),
+        Uint128::from(20u128),
+    )
+    .unwrap();
+
+    // the second fill takes the rest of the pool, the third none
+    let mut bounties: Vec<String> = vec![];
+    for (order_id, ask_balance_after) in [(1u64, 1000u128), (2u64, 2000u128), (3u64, 3000u128)] {
+        let res = fill_order(&mut deps, &oracle, order_id, ask_balance_after);
+        let bounty = res
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "bounty_amount")
+            .unwrap();
+        bounties.push(bounty.value.clone());
+    }
+    assert_eq!(bounties, vec!["20", "5", "0"]);
+    assert_eq!(BOUNTY_POOL.load(&deps.storage).unwrap(), Uint128::zero());
+}
+
+#[test]
+fn native_refund_dust_is_withheld_and_swept() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            native_refund_dust: Some(Uint128::from(3u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one()).with_price(PAIR, uluna(), Decimal::one());
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: BIDDER.to_string(),
+            amount: vec![Coin::new(997u128, "uluna")],
+        })
+    );
+    assert_eq!(
+        ACCRUED_NATIVE_DUST
+            .load(&deps.storage, "uluna".as_bytes())
+            .unwrap(),
+        Uint128::from(3u128)
+    );
+
+    let res =
+        sweep_native_dust(deps.as_mut(), mock_info("owner", &[]), "uluna".to_string()).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "owner".to_string(),
+            amount: vec![Coin::new(3u128, "uluna")],
+        })
+    );
+    let err =
+        sweep_native_dust(deps.as_mut(), mock_info("owner", &[]), "uluna".to_string()).unwrap_err();
+    assert_eq!(err, StdError::generic_err("no dust accrued in denom"));
+}
+
+#[test]
+fn native_refund_dust_is_withheld_before_the_refund_swap() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            native_refund_dust: Some(Uint128::from(3u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one()).with_price(PAIR, uluna(), Decimal::one());
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // the offer is swapped back into the token it asks
+    let res = cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        Some(offer_token()),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: PAIR.to_string(),
+            funds: vec![Coin::new(997u128, "uluna")],
+            msg: to_binary(&PairExecuteMsg::Swap {
+                offer_asset: Asset {
+                    info: uluna(),
+                    amount: Uint128::from(997u128),
+                },
+                belief_price: None,
+                max_spread: Some(Decimal::percent(5)),
+                to: Some(BIDDER.to_string()),
+            })
+            .unwrap(),
+        })
+    );
+    assert_eq!(
+        ACCRUED_NATIVE_DUST
+            .load(&deps.storage, "uluna".as_bytes())
+            .unwrap(),
+        Uint128::from(3u128)
+    );
+}
+
+#[test]
+fn min_order_lifetime_delays_the_bidder_cancel() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            min_order_lifetime_seconds: Some(600u64),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    let now: u64 = mock_env().block.time.seconds();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(599u64);
+    let err = cancel_order(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "order 1 cannot be canceled before {}",
+            now + 600u64
+        ))
+    );
+
+    // the owner is not bound by the min lifetime
+    force_cancel_order(deps.as_mut(), mock_info("owner", &[]), 2u64).unwrap();
+
+    env.block.time = env.block.time.plus_seconds(1u64);
+    cancel_order(
+        deps.as_mut(),
+        env,
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn incentive_is_paid_per_fill_until_the_pool_is_drained() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let err = deposit_incentive(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        Uint128::from(15u128),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("no incentive token is configured")
+    );
+
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            incentive_token: Some("incentive0000".to_string()),
+            incentive_per_fill: Some(Uint128::from(10u128)),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..3 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+
+    let err = deposit_incentive(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        Uint128::from(15u128),
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("unauthorized"));
+    let res = deposit_incentive(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        Uint128::from(15u128),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "incentive0000".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: "owner".to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(15u128),
+            })
+            .unwrap(),
+        })
+    );
+
+    // the second fill takes the rest of the pool, the third none
+    let mut incentives: Vec<String> = vec![];
+    for (order_id, ask_balance_after) in [(1u64, 1000u128), (2u64, 2000u128), (3u64, 3000u128)] {
+        let res = fill_order(&mut deps, &oracle, order_id, ask_balance_after);
+        let incentive = res
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "incentive_amount")
+            .unwrap();
+        incentives.push(incentive.value.clone());
+    }
+    assert_eq!(incentives, vec!["10", "5", "0"]);
+    assert_eq!(INCENTIVE_POOL.load(&deps.storage).unwrap(), Uint128::zero());
+}
@@ -0,0 +1,1741 @@
+use std::str::FromStr;
+
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, QuerierWrapper, StdError, StdResult, Uint128};
+use terraswap::asset::{Asset, AssetInfo, PairInfo};
+use terraswap::pair::SimulationResponse;
+
+use crate::contract::instantiate;
+use crate::msg::{
+    AllowanceCheckResponse, FeeTokenInfoResponse, InstantiateMsg, OrderBy, OrderSide, OrderSort,
+    OrderStatsResponse, OrdersResponse, PairStatsResponse, PreviewExecutionResponse, PriceCursor,
+    PrioritizedOrdersResponse, TransferPreview,
+};
+use crate::order::{cancel_order, submit_order};
+use crate::querier::PairOracle;
+use crate::query::{
+    query_allowance_check, query_config, query_executor_stats, query_fee_token_info, query_fills,
+    query_has_open_orders, query_max_fill_within_spread, query_median_price,
+    query_optimal_fill_set, query_order, query_order_counts_by_pair, query_order_range,
+    query_order_stats, query_order_with_bidder_context, query_orders, query_orders_by_ask_asset,
+    query_orders_by_denom, query_orders_near_price, query_orphaned_orders, query_pair_stats,
+    query_preview_execution, query_preview_submit, query_prioritized_orders,
+    query_profitable_orders, query_recent, query_top_bidders, query_total_escrowed_fees,
+    query_underwater_orders,
+};
+use crate::state::{OrderInfo, ORDERS};
+use crate::testing::mock_oracle::MockOracle;
+use crate::testing::mock_querier::mock_dependencies;
+use crate::testing::{
+    fill_order, instantiate_msg, mock_oracle, offer_token, submit_order_as, submit_token_order,
+    uluna, BIDDER, EXECUTOR, FACTORY, FEE_TOKEN, OFFER_TOKEN, PAIR,
+};
+
+const FEE_PAIR: &str = "pair0001";
+
+// the offer swaps 1:1 into uluna, which is worth half a fee token
+fn valuing_oracle() -> MockOracle {
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+
+    mock_oracle(Decimal::one())
+        .with_pair(FACTORY, FEE_PAIR, [uluna(), fee_token])
+        .with_price(FEE_PAIR, uluna(), Decimal::percent(50))
+}
+
+fn order_ids(res: &OrdersResponse) -> Vec<u64> {
+    res.orders.iter().map(|order| order.order_id).collect()
+}
+
+#[test]
+fn profitable_orders_by_gas_cost() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = valuing_oracle();
+    // reward of 10 + 100 / 2
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    // reward of 10, no excess
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1000, 10);
+    // not fillable
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1100, 100);
+
+    let query = |gas_cost: u128, start_after: Option<u64>, limit: Option<u32>| {
+        query_profitable_orders(
+            deps.as_ref(),
+            mock_env(),
+            &oracle,
+            Uint128::from(gas_cost),
+            start_after,
+            limit,
+        )
+        .unwrap()
+    };
+
+    assert_eq!(order_ids(&query(5, None, None)), vec![1u64, 2u64]);
+    assert_eq!(order_ids(&query(60, None, None)), vec![1u64]);
+    assert_eq!(order_ids(&query(61, None, None)), Vec::<u64>::new());
+    assert_eq!(query(5, None, None).next_start_after, None);
+
+    // the scan is bounded by limit and continues after the last scanned order
+    let page = query(5, Some(1u64), Some(1u32));
+    assert_eq!(order_ids(&page), vec![2u64]);
+    assert_eq!(page.next_start_after, Some(2u64));
+    let page = query(5, page.next_start_after, Some(1u32));
+    assert_eq!(order_ids(&page), Vec::<u64>::new());
+    assert_eq!(page.next_start_after, Some(3u64));
+    let page = query(5, page.next_start_after, Some(1u32));
+    assert_eq!(page.next_start_after, None);
+}
+
+#[test]
+fn optimal_fill_set_takes_the_best_rewards_greedily() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = valuing_oracle();
+    // rewards of 60, 10, 35 and 35
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1000, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 950, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 950, 10);
+
+    let query = |budget: u64, start_after: Option<u64>, limit: Option<u32>| {
+        query_optimal_fill_set(
+            deps.as_ref(),
+            mock_env(),
+            &oracle,
+            100u64,
+            budget,
+            start_after,
+            limit,
+        )
+        .unwrap()
+    };
+
+    let res = query(250, None, None);
+    assert_eq!(res.order_ids, vec![1u64, 3u64]);
+    assert_eq!(res.total_reward, Uint128::from(95u128));
+    assert_eq!(res.next_start_after, None);
+
+    // ties are broken by ascending order_id
+    let res = query(350, None, None);
+    assert_eq!(res.order_ids, vec![1u64, 3u64, 4u64]);
+    assert_eq!(res.total_reward, Uint128::from(130u128));
+
+    // only the limit orders after start_after are simulated
+    let res = query(1000, None, Some(2u32));
+    assert_eq!(res.order_ids, vec![1u64, 2u64]);
+    assert_eq!(res.next_start_after, Some(2u64));
+    let res = query(1000, res.next_start_after, Some(2u32));
+    assert_eq!(res.order_ids, vec![3u64, 4u64]);
+    assert_eq!(res.next_start_after, Some(4u64));
+}
+
+#[test]
+fn underwater_orders_by_shortfall() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    // fillable, 20% and 1% short of the return of 1000
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1250, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1010, 10);
+
+    let query = |deps: Deps,
+                 min_shortfall_bps: u64,
+                 start_after: Option<PriceCursor>,
+                 limit: Option<u32>| {
+        let res = query_underwater_orders(
+            deps,
+            &oracle,
+            PAIR.to_string(),
+            min_shortfall_bps,
+            start_after,
+            limit,
+        )
+        .unwrap();
+        let order_ids: Vec<u64> = res.orders.iter().map(|order| order.order_id).collect();
+        (order_ids, res.next_start_after)
+    };
+
+    assert_eq!(query(deps.as_ref(), 1000, None, None).0, vec![2u64]);
+    // ascending by implied price
+    assert_eq!(query(deps.as_ref(), 0, None, None).0, vec![3u64, 2u64]);
+
+    // the scan is bounded by limit and continues after the last scanned order
+    let (order_ids, next_start_after) = query(deps.as_ref(), 1000, None, Some(2u32));
+    assert_eq!(order_ids, Vec::<u64>::new());
+    assert_eq!(
+        next_start_after,
+        Some(PriceCursor {
+            offer_asset_info: offer_token(),
+            price: ORDERS
+                .load(&deps.storage, &3u64.to_be_bytes())
+                .unwrap()
+                .implied_price(),
+            order_id: 3u64,
+        })
+    );
+    let (order_ids, next_start_after) = query(deps.as_ref(), 1000, next_start_after, Some(2u32));
+    assert_eq!(order_ids, vec![2u64]);
+    assert_eq!(next_start_after, None);
+
+    // the cursor stays valid once its order is closed
+    let (_, next_start_after) = query(deps.as_ref(), 0, None, Some(1u32));
+    assert_eq!(
+        next_start_after.as_ref().map(|cursor| cursor.order_id),
+        Some(1u64)
+    );
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    let (order_ids, _) = query(deps.as_ref(), 0, next_start_after, None);
+    assert_eq!(order_ids, vec![3u64, 2u64]);
+}
+
+#[test]
+fn prioritized_orders_by_fee_and_age() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let now: u64 = mock_env().block.time.seconds() + 100u64;
+    // (fee, age) of orders 1 to 4
+    for (fee_amount, age) in [
+        (10u128, 100u64),
+        (20u128, 40u64),
+        (30u128, 0u64),
+        (10u128, 100u64),
+    ] {
+        let order_id: u64 = submit_token_order(deps.as_mut(), &oracle, 1000, 900, fee_amount);
+        let mut order: OrderInfo = ORDERS.load(&deps.storage, &order_id.to_be_bytes()).unwrap();
+        order.created_at = now - age;
+        ORDERS
+            .save(&mut deps.storage, &order_id.to_be_bytes(), &order)
+            .unwrap();
+    }
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100u64);
+    let res: PrioritizedOrdersResponse =
+        query_prioritized_orders(deps.as_ref(), env, PAIR.to_string(), None, None).unwrap();
+    // fee * (1 + age), ties broken by the oldest order id
+    assert_eq!(
+        res.orders
+            .iter()
+            .map(|order| (order.order.order_id, order.priority_score.u128()))
+            .collect::<Vec<(u64, u128)>>(),
+        vec![
+            (1u64, 1010u128),
+            (4u64, 1010u128),
+            (2u64, 820u128),
+            (3u64, 30u128)
+        ]
+    );
+}
+
+#[test]
+fn prioritized_orders_rank_every_order_page_by_page() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..100 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    // the best paying order has the highest implied price, past the first page
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1000, 50);
+
+    let res: PrioritizedOrdersResponse =
+        query_prioritized_orders(deps.as_ref(), mock_env(), PAIR.to_string(), None, None).unwrap();
+    assert_eq!(res.orders.len(), 10);
+    assert!(res
+        .orders
+        .iter()
+        .all(|order| order.order.order_id <= 100u64));
+    assert_eq!(
+        res.next_start_after.as_ref().map(|cursor| cursor.order_id),
+        Some(100u64)
+    );
+
+    let res: PrioritizedOrdersResponse = query_prioritized_orders(
+        deps.as_ref(),
+        mock_env(),
+        PAIR.to_string(),
+        res.next_start_after,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.orders
+            .iter()
+            .map(|order| order.order.order_id)
+            .collect::<Vec<u64>>(),
+        vec![101u64]
+    );
+    assert_eq!(res.next_start_after, None);
+}
+
+#[test]
+fn orders_near_price_rejects_an_overflowing_band() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+
+    let res = query_orders_near_price(
+        deps.as_ref(),
+        PAIR.to_string(),
+        offer_token(),
+        Decimal::from_str("300000000000000000000").unwrap(),
+        10000u64,
+        None,
+    );
+    assert_eq!(
+        res,
+        Err(StdError::generic_err(
+            "target_price overflows at the top of the band"
+        ))
+    );
+}
+
+#[test]
+fn orders_near_price_of_one_side() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    // implied prices of 0.9, 1.5 and 0.95
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1500, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 950, 10);
+    // the other side of the pair at a price of 0.9
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let query = |offer_asset_info: AssetInfo| {
+        query_orders_near_price(
+            deps.as_ref(),
+            PAIR.to_string(),
+            offer_asset_info,
+            Decimal::percent(90),
+            1000u64,
+            None,
+        )
+        .unwrap()
+    };
+
+    assert_eq!(order_ids(&query(offer_token())), vec![1u64, 3u64]);
+    assert_eq!(order_ids(&query(uluna())), vec![4u64]);
+
+    // a scan of the whole pair goes side by side, by price within a side
+    let mut scanned: Vec<u64> = vec![];
+    let mut start_after: Option<PriceCursor> = None;
+    loop {
+        start_after = query_underwater_orders(
+            deps.as_ref(),
+            &oracle,
+            PAIR.to_string(),
+            0u64,
+            start_after,
+            Some(1u32),
+        )
+        .unwrap()
+        .next_start_after;
+        match &start_after {
+            Some(cursor) => scanned.push(cursor.order_id),
+            None => break,
+        }
+    }
+    assert_eq!(scanned, vec![4u64, 1u64, 3u64, 2u64]);
+}
+
+#[test]
+fn preview_submit_transfers_every_escrowed_fee() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+
+    // a fee token offer is transferred along with the fee, fee bond and rebuy fee
+    let res = query_preview_submit(
+        deps.as_ref(),
+        BIDDER.to_string(),
+        Asset {
+            info: fee_token.clone(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        Uint128::from(10u128),
+        Some(Uint128::from(5u128)),
+        Some(Uint128::from(20u128)),
+    )
+    .unwrap();
+    assert_eq!(
+        res.transfers,
+        vec![TransferPreview {
+            from: BIDDER.to_string(),
+            asset: Asset {
+                info: fee_token,
+                amount: Uint128::from(1035u128),
+            },
+            allowance_required: true,
+        }]
+    );
+}
+
+#[test]
+fn fees_denominated_in_the_ask_asset() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            fee_denominated_in_ask: Some(true),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+
+    let res = query_preview_submit(
+        deps.as_ref(),
+        BIDDER.to_string(),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        Uint128::from(10u128),
+        Some(Uint128::from(5u128)),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.transfers,
+        vec![
+            TransferPreview {
+                from: BIDDER.to_string(),
+                asset: Asset {
+                    info: offer_token(),
+                    amount: Uint128::from(1000u128),
+                },
+                allowance_required: true,
+            },
+            TransferPreview {
+                from: BIDDER.to_string(),
+                asset: Asset {
+                    info: uluna(),
+                    amount: Uint128::from(15u128),
+                },
+                allowance_required: false,
+            },
+        ]
+    );
+
+    for fee_amount in [10u128, 20u128] {
+        submit_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &[Coin::new(fee_amount, "uluna")]),
+            &oracle,
+            Asset {
+                info: offer_token(),
+                amount: Uint128::from(1000u128),
+            },
+            Asset {
+                info: uluna(),
+                amount: Uint128::from(900u128),
+            },
+            Uint128::from(fee_amount),
+            None,
+            Uint128::zero(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    // the fees are summed in their own asset, apart from the fee token
+    let res = query_total_escrowed_fees(deps.as_ref()).unwrap();
+    assert_eq!(res.total_escrowed_fees, Uint128::zero());
+    assert_eq!(
+        res.ask_escrowed_fees,
+        vec![Asset {
+            info: uluna(),
+            amount: Uint128::from(30u128),
+        }]
+    );
+
+    for order_id in [1u64, 2u64] {
+        cancel_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &[]),
+            &oracle,
+            order_id,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+    let res = query_total_escrowed_fees(deps.as_ref()).unwrap();
+    assert_eq!(res.ask_escrowed_fees, vec![]);
+}
+
+#[test]
+fn orders_above_a_min_offer_amount() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for offer_amount in [500u128, 1000u128, 1500u128] {
+        submit_token_order(deps.as_mut(), &oracle, offer_amount, 400, 10);
+    }
+
+    let res = query_orders(
+        deps.as_ref(),
+        None,
+        None,
+        None,
+        Some(OrderBy::Asc),
+        Some(Uint128::from(1000u128)),
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![2u64, 3u64]);
+
+    // only the page is scanned, the cursor follows the last order read
+    let res = query_orders(
+        deps.as_ref(),
+        None,
+        None,
+        Some(2u32),
+        Some(OrderBy::Asc),
+        Some(Uint128::from(1500u128)),
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), Vec::<u64>::new());
+    assert_eq!(res.next_start_after, Some(2u64));
+    let res = query_orders(
+        deps.as_ref(),
+        None,
+        Some(2u64),
+        Some(2u32),
+        Some(OrderBy::Asc),
+        Some(Uint128::from(1500u128)),
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![3u64]);
+    assert_eq!(res.next_start_after, None);
+}
+
+#[test]
+fn executor_stats_track_the_last_fill() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = query_executor_stats(deps.as_ref(), EXECUTOR.to_string()).unwrap();
+    assert_eq!(res.fill_count, 0u64);
+    assert_eq!(res.last_filled_order_id, None);
+
+    fill_order(&mut deps, &oracle, 2u64, 1000);
+    fill_order(&mut deps, &oracle, 1u64, 2000);
+    let res = query_executor_stats(deps.as_ref(), EXECUTOR.to_string()).unwrap();
+    assert_eq!(res.fill_count, 2u64);
+    assert_eq!(res.last_filled_order_id, Some(1u64));
+}
+
+#[test]
+fn order_range_skips_cancelled_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..5 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        3u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let res = query_order_range(deps.as_ref(), 2u64, 4u64).unwrap();
+    assert_eq!(order_ids(&res), vec![2u64, 4u64]);
+    assert_eq!(res.next_start_after, None);
+
+    let res = query_order_range(deps.as_ref(), 4u64, 100u64).unwrap();
+    assert_eq!(order_ids(&res), vec![4u64, 5u64]);
+}
+
+#[test]
+fn has_open_orders_of_a_bidder() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let res = query_has_open_orders(deps.as_ref(), BIDDER.to_string()).unwrap();
+    assert!(res.has_open_orders);
+    let res = query_has_open_orders(deps.as_ref(), "addr0001".to_string()).unwrap();
+    assert!(!res.has_open_orders);
+
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    let res = query_has_open_orders(deps.as_ref(), BIDDER.to_string()).unwrap();
+    assert!(!res.has_open_orders);
+}
+
+#[test]
+fn preview_submit_transfers_the_offer_and_the_fee() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_asset = Asset {
+        info: AssetInfo::Token {
+            contract_addr: FEE_TOKEN.to_string(),
+        },
+        amount: Uint128::from(10u128),
+    };
+
+    let res = query_preview_submit(
+        deps.as_ref(),
+        BIDDER.to_string(),
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(1000u128),
+        },
+        uluna(),
+        Uint128::from(10u128),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.transfers,
+        vec![
+            TransferPreview {
+                from: BIDDER.to_string(),
+                asset: Asset {
+                    info: offer_token(),
+                    amount: Uint128::from(1000u128),
+                },
+                allowance_required: true,
+            },
+            TransferPreview {
+                from: BIDDER.to_string(),
+                asset: fee_asset.clone(),
+                allowance_required: true,
+            },
+        ]
+    );
+
+    // a native offer is sent along with the message
+    let res = query_preview_submit(
+        deps.as_ref(),
+        BIDDER.to_string(),
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        offer_token(),
+        Uint128::from(10u128),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.transfers,
+        vec![
+            TransferPreview {
+                from: BIDDER.to_string(),
+                asset: Asset {
+                    info: uluna(),
+                    amount: Uint128::from(1000u128),
+                },
+                allowance_required: false,
+            },
+            TransferPreview {
+                from: BIDDER.to_string(),
+                asset: fee_asset,
+                allowance_required: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn order_counts_by_pair_pages_over_pairs() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let mut oracle = mock_oracle(Decimal::one());
+    for (token, pair) in [("token0001", "pair0001"), ("token0002", "pair0002")] {
+        let token = AssetInfo::Token {
+            contract_addr: token.to_string(),
+        };
+        oracle = oracle
+            .with_pair(FACTORY, pair, [token.clone(), uluna()])
+            .with_price(pair, token, Decimal::one());
+    }
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0001");
+    submit_order_as(deps.as_mut(), &oracle, "addr0001", "token0001");
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0002");
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0002");
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0002");
+
+    let res = query_order_counts_by_pair(deps.as_ref(), None, Some(2u32)).unwrap();
+    assert_eq!(
+        res.order_counts,
+        vec![(PAIR.to_string(), 1u64), ("pair0001".to_string(), 2u64)]
+    );
+    let res =
+        query_order_counts_by_pair(deps.as_ref(), Some("pair0001".to_string()), None).unwrap();
+    assert_eq!(res.order_counts, vec![("pair0002".to_string(), 3u64)]);
+}
+
+#[test]
+fn orders_return_the_next_cursor() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..3 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+
+    let res = query_orders(
+        deps.as_ref(),
+        None,
+        None,
+        Some(2u32),
+        Some(OrderBy::Asc),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![1u64, 2u64]);
+    assert_eq!(res.next_start_after, Some(2u64));
+
+    let res = query_orders(
+        deps.as_ref(),
+        None,
+        res.next_start_after,
+        Some(2u32),
+        Some(OrderBy::Asc),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![3u64]);
+    assert_eq!(res.next_start_after, None);
+}
+
+#[test]
+fn orders_page_across_cancelled_ids_in_both_directions() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..10 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    for order_id in (2u64..=10u64).step_by(2) {
+        cancel_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &[]),
+            &oracle,
+            order_id,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    // follows next_start_after to the last page, over all orders or those of the bidder
+    let page_through = |bidder_addr: Option<String>, order_by: OrderBy| {
+        let mut scanned: Vec<u64> = vec![];
+        let mut start_after: Option<u64> = None;
+        loop {
+            let res = query_orders(
+                deps.as_ref(),
+                bidder_addr.clone(),
+                start_after,
+                Some(2u32),
+                Some(order_by.clone()),
+                None,
+                None,
+            )
+            .unwrap();
+            scanned.extend(order_ids(&res));
+            start_after = res.next_start_after;
+            if start_after.is_none() {
+                return scanned;
+            }
+        }
+    };
+
+    for bidder_addr in [None, Some(BIDDER.to_string())] {
+        assert_eq!(
+            page_through(bidder_addr.clone(), OrderBy::Asc),
+            vec![1u64, 3u64, 5u64, 7u64, 9u64]
+        );
+        assert_eq!(
+            page_through(bidder_addr, OrderBy::Desc),
+            vec![9u64, 7u64, 5u64, 3u64, 1u64]
+        );
+    }
+}
+
+#[test]
+fn order_hash_is_recomputable() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    // sha256 of "addr0000|1000token0000|900uluna|10|1571797419|1"
+    let res = query_order(deps.as_ref(), 1u64).unwrap();
+    assert_eq!(
+        res.order_hash,
+        "c03bcc17e4426f8044053f133521a4079e985e9f208332448619bb33aedc6fce"
+    );
+}
+
+#[test]
+fn orders_sorted_by_each_mode() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    // (ask amount, fee, seconds after now)
+    for (ask_amount, fee_amount, age) in [(950u128, 20u128, 300u64), (900, 10, 100), (800, 30, 200)]
+    {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(age);
+        submit_order(
+            deps.as_mut(),
+            env,
+            mock_info(BIDDER, &[]),
+            &oracle,
+            Asset {
+                info: offer_token(),
+                amount: Uint128::from(1000u128),
+            },
+            Asset {
+                info: uluna(),
+                amount: Uint128::from(ask_amount),
+            },
+            Uint128::from(fee_amount),
+            None,
+            Uint128::zero(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    let sorted_ids = |sort_by: OrderSort| {
+        let res = query_orders(
+            deps.as_ref(),
+            None,
+            None,
+            None,
+            Some(OrderBy::Asc),
+            None,
+            Some(sort_by),
+        )
+        .unwrap();
+        order_ids(&res)
+    };
+    assert_eq!(sorted_ids(OrderSort::Id), vec![1u64, 2u64, 3u64]);
+    assert_eq!(sorted_ids(OrderSort::Price), vec![3u64, 2u64, 1u64]);
+    assert_eq!(sorted_ids(OrderSort::Fee), vec![3u64, 1u64, 2u64]);
+    assert_eq!(sorted_ids(OrderSort::Age), vec![2u64, 3u64, 1u64]);
+}
+
+#[test]
+fn recent_returns_the_config_and_the_newest_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..4 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+
+    let res = query_recent(deps.as_ref(), 2u32).unwrap();
+    assert_eq!(res.config, query_config(deps.as_ref()).unwrap());
+    assert_eq!(
+        res.orders
+            .iter()
+            .map(|order| order.order_id)
+            .collect::<Vec<u64>>(),
+        vec![4u64, 3u64]
+    );
+
+    // capped at the max limit
+    for _ in 0..30 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    let res = query_recent(deps.as_ref(), 100u32).unwrap();
+    assert_eq!(res.orders.len(), 30usize);
+    assert_eq!(res.orders[0].order_id, 34u64);
+}
+
+#[test]
+fn orders_by_ask_asset_isolate_the_ask_asset() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one()).with_price(PAIR, uluna(), Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[Coin::new(1000u128, "uluna")]),
+        &oracle,
+        Asset {
+            info: uluna(),
+            amount: Uint128::from(1000u128),
+        },
+        Asset {
+            info: offer_token(),
+            amount: Uint128::from(900u128),
+        },
+        Uint128::from(10u128),
+        None,
+        Uint128::zero(),
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        3u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    let res = query_orders_by_ask_asset(deps.as_ref(), uluna(), None, None).unwrap();
+    assert_eq!(order_ids(&res), vec![1u64, 4u64]);
+    let res = query_orders_by_ask_asset(deps.as_ref(), offer_token(), None, None).unwrap();
+    assert_eq!(order_ids(&res), vec![2u64]);
+    let res = query_orders_by_ask_asset(deps.as_ref(), uluna(), Some(1u64), None).unwrap();
+    assert_eq!(order_ids(&res), vec![4u64]);
+}
+
+#[test]
+fn order_with_bidder_context_sums_the_bidder_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let token = AssetInfo::Token {
+        contract_addr: "token0001".to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [token.clone(), uluna()])
+        .with_price("pair0001", token, Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 500, 450, 10);
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0001");
+    submit_order_as(deps.as_mut(), &oracle, "addr0001", OFFER_TOKEN);
+
+    // the order in token0001 is counted but not escrowed in the offer asset
+    let res = query_order_with_bidder_context(deps.as_ref(), 1u64).unwrap();
+    assert_eq!(res.order.order_id, 1u64);
+    assert_eq!(res.bidder_open_order_count, 3u64);
+    assert_eq!(res.bidder_escrowed_offer, Uint128::from(1500u128));
+
+    let res = query_order_with_bidder_context(deps.as_ref(), 4u64).unwrap();
+    assert_eq!(res.bidder_open_order_count, 1u64);
+    assert_eq!(res.bidder_escrowed_offer, Uint128::from(1000u128));
+}
+
+#[test]
+fn median_price_of_odd_and_even_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    let median_price = |deps: Deps, offer_asset_info: AssetInfo| {
+        query_median_price(deps, PAIR.to_string(), offer_asset_info)
+            .unwrap()
+            .median_price
+    };
+    assert_eq!(median_price(deps.as_ref(), offer_token()), None);
+
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 800, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 950, 10);
+    assert_eq!(
+        median_price(deps.as_ref(), offer_token()),
+        Some(Decimal::percent(90))
+    );
+
+    submit_token_order(deps.as_mut(), &oracle, 1000, 1000, 10);
+    assert_eq!(
+        median_price(deps.as_ref(), offer_token()),
+        Some(Decimal::permille(925))
+    );
+
+    // the other side of the pair has no orders
+    assert_eq!(median_price(deps.as_ref(), uluna()), None);
+}
+
+#[test]
+fn orphaned_orders_lists_orders_without_a_pair() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let token = AssetInfo::Token {
+        contract_addr: "token0001".to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [token.clone(), uluna()])
+        .with_price("pair0001", token, Decimal::one());
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, "token0001");
+
+    // the factory no longer resolves the token0001 pair
+    let res =
+        query_orphaned_orders(deps.as_ref(), &mock_oracle(Decimal::one()), None, None).unwrap();
+    assert_eq!(order_ids(&res), vec![2u64]);
+    assert_eq!(res.next_start_after, None);
+
+    let res = query_orphaned_orders(
+        deps.as_ref(),
+        &mock_oracle(Decimal::one()),
+        None,
+        Some(1u32),
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), Vec::<u64>::new());
+    assert_eq!(res.next_start_after, Some(1u64));
+}
+
+#[test]
+fn fee_token_info_reads_the_token_metadata() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_decimals(FEE_TOKEN, 8u8);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        query_fee_token_info(deps.as_ref()).unwrap(),
+        FeeTokenInfoResponse {
+            fee_token: FEE_TOKEN.to_string(),
+            name: FEE_TOKEN.to_string(),
+            symbol: "TKN".to_string(),
+            decimals: 8u8,
+        }
+    );
+}
+
+// a pair of the given offer reserve whose swap of amount loses amount^2 / reserve to the spread
+struct SpreadOracle(u128);
+
+impl PairOracle for SpreadOracle {
+    fn query_pair_info(
+        &self,
+        _querier: &QuerierWrapper,
+        _factory_addr: Addr,
+        _asset_infos: &[AssetInfo; 2],
+    ) -> StdResult<PairInfo> {
+        Err(StdError::generic_err("no pair"))
+    }
+
+    fn simulate(
+        &self,
+        _querier: &QuerierWrapper,
+        _pair_addr: Addr,
+        offer_asset: &Asset,
+    ) -> StdResult<SimulationResponse> {
+        let spread_amount: Uint128 = offer_asset
+            .amount
+            .multiply_ratio(offer_asset.amount, self.0);
+
+        Ok(SimulationResponse {
+            return_amount: offer_asset.amount.checked_sub(spread_amount)?,
+            spread_amount,
+            commission_amount: Uint128::zero(),
+        })
+    }
+}
+
+#[test]
+fn max_fill_within_spread_searches_the_largest_amount() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 5000, 4000, 10);
+    let oracle = SpreadOracle(100_000u128);
+
+    // 1048^2 / 100000 rounds down to 10, within 1% of 1048, 1049 loses 11
+    let res = query_max_fill_within_spread(deps.as_ref(), &oracle, 1u64, 100u64).unwrap();
+    assert_eq!(res.fill_amount, Uint128::from(1048u128));
+
+    // the whole order is within a 5% spread
+    let res = query_max_fill_within_spread(deps.as_ref(), &oracle, 1u64, 500u64).unwrap();
+    assert_eq!(res.fill_amount, Uint128::from(5000u128));
+
+    let err = query_max_fill_within_spread(deps.as_ref(), &oracle, 1u64, 10001u64).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("max_spread_bps must not exceed 10000")
+    );
+}
+
+#[test]
+fn allowance_check_against_the_cw20_allowance() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .with_token_allowance(FEE_TOKEN, BIDDER, MOCK_CONTRACT_ADDR, 1000u128);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let fee_token = AssetInfo::Token {
+        contract_addr: FEE_TOKEN.to_string(),
+    };
+    let allowance_check = |offer_asset: Asset, fee_amount: u128| {
+        query_allowance_check(
+            deps.as_ref(),
+            mock_env(),
+            BIDDER.to_string(),
+            offer_asset,
+            uluna(),
+            Uint128::from(fee_amount),
+            None,
+            None,
+        )
+        .unwrap()
+    };
+
+    // a fee token offer requires the allowance of the offer and the fee
+    assert_eq!(
+        allowance_check(
+            Asset {
+                info: fee_token.clone(),
+                amount: Uint128::from(990u128),
+            },
+            10u128
+        ),
+        AllowanceCheckResponse {
+            sufficient: true,
+            current: Uint128::from(1000u128),
+            required: Uint128::from(1000u128),
+        }
+    );
+    assert_eq!(
+        allowance_check(
+            Asset {
+                info: fee_token,
+                amount: Uint128::from(991u128),
+            },
+            10u128
+        ),
+        AllowanceCheckResponse {
+            sufficient: false,
+            current: Uint128::from(1000u128),
+            required: Uint128::from(1001u128),
+        }
+    );
+
+    // no allowance to the offer token
+    assert_eq!(
+        allowance_check(
+            Asset {
+                info: offer_token(),
+                amount: Uint128::from(500u128),
+            },
+            10u128
+        ),
+        AllowanceCheckResponse {
+            sufficient: false,
+            current: Uint128::zero(),
+            required: Uint128::from(500u128),
+        }
+    );
+
+    // native offers need no allowance
+    assert!(
+        allowance_check(
+            Asset {
+                info: uluna(),
+                amount: Uint128::from(500u128),
+            },
+            10u128
+        )
+        .sufficient
+    );
+}
+
+#[test]
+fn orders_by_an_ibc_denom() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let ibc_denom = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+    let ibc_asset_info = AssetInfo::NativeToken {
+        denom: ibc_denom.to_string(),
+    };
+    let oracle = mock_oracle(Decimal::one())
+        .with_pair(FACTORY, "pair0001", [offer_token(), ibc_asset_info.clone()])
+        .with_price("pair0001", offer_token(), Decimal::one())
+        .with_price("pair0001", ibc_asset_info.clone(), Decimal::one());
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    for (sent, offer_asset, ask_asset) in [
+        (vec![], offer_token(), ibc_asset_info.clone()),
+        (
+            vec![Coin::new(1000u128, ibc_denom)],
+            ibc_asset_info.clone(),
+            offer_token(),
+        ),
+    ] {
+        submit_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &sent),
+            &oracle,
+            Asset {
+                info: offer_asset,
+                amount: Uint128::from(1000u128),
+            },
+            Asset {
+                info: ask_asset,
+                amount: Uint128::from(900u128),
+            },
+            Uint128::from(10u128),
+            None,
+            Uint128::zero(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    let res = query_orders_by_denom(
+        deps.as_ref(),
+        ibc_denom.to_string(),
+        OrderSide::Ask,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![2u64]);
+    let res = query_orders_by_denom(
+        deps.as_ref(),
+        ibc_denom.to_string(),
+        OrderSide::Offer,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![3u64]);
+    let res = query_orders_by_denom(
+        deps.as_ref(),
+        "uluna".to_string(),
+        OrderSide::Ask,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(order_ids(&res), vec![1u64]);
+}
+
+#[test]
+fn order_stats_after_creating_and_cancelling_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..3 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        2u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        query_order_stats(deps.as_ref()).unwrap(),
+        OrderStatsResponse {
+            last_order_id: 3u64,
+            total_created: 3u64,
+            open_count: 2u64,
+        }
+    );
+}
+
+#[test]
+fn top_bidders_count_every_submission() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    submit_order_as(deps.as_mut(), &oracle, "addr0001", OFFER_TOKEN);
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    let res = query_top_bidders(deps.as_ref(), None, None).unwrap();
+    assert_eq!(
+        res.bidders,
+        vec![(BIDDER.to_string(), 2u64), ("addr0001".to_string(), 1u64)]
+    );
+
+    // the totals are cumulative, a cancelled order is still counted
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+    submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    let res = query_top_bidders(deps.as_ref(), Some("addr0000".to_string()), None).unwrap();
+    assert_eq!(res.bidders, vec![("addr0001".to_string(), 1u64)]);
+    let res = query_top_bidders(deps.as_ref(), None, Some(1u32)).unwrap();
+    assert_eq!(res.bidders, vec![(BIDDER.to_string(), 3u64)]);
+}
+
+#[test]
+fn orders_report_the_total_open_orders() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..3 {
+        submit_order_as(deps.as_mut(), &oracle, BIDDER, OFFER_TOKEN);
+    }
+    submit_order_as(deps.as_mut(), &oracle, "addr0001", OFFER_TOKEN);
+    cancel_order(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(BIDDER, &[]),
+        &oracle,
+        1u64,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // the total is not limited to the page
+    let res = query_orders(deps.as_ref(), None, None, Some(1u32), None, None, None).unwrap();
+    assert_eq!(res.orders.len(), 1usize);
+    assert_eq!(res.total_open, Some(3u64));
+
+    let res = query_orders(
+        deps.as_ref(),
+        Some(BIDDER.to_string()),
+        None,
+        Some(1u32),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(res.total_open, Some(2u64));
+}
+
+#[test]
+fn preview_execution_matches_the_settlement() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::percent(95));
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+
+    let preview: PreviewExecutionResponse = query_preview_execution(
+        deps.as_ref(),
+        mock_env(),
+        &oracle,
+        1u64,
+        EXECUTOR.to_string(),
+    )
+    .unwrap();
+    assert_eq!(
+        preview,
+        PreviewExecutionResponse {
+            bidder_receives: Asset {
+                info: uluna(),
+                amount: Uint128::from(900u128),
+            },
+            executor_fee: Asset {
+                info: AssetInfo::Token {
+                    contract_addr: FEE_TOKEN.to_string(),
+                },
+                amount: Uint128::from(10u128),
+            },
+            executor_excess: Asset {
+                info: uluna(),
+                amount: Uint128::from(50u128),
+            },
+        }
+    );
+
+    let res = fill_order(&mut deps, &oracle, 1u64, 950);
+    let attr_value = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(
+        attr_value("fee_amount"),
+        preview.executor_fee.amount.to_string()
+    );
+    assert_eq!(
+        attr_value("excess_amount"),
+        preview.executor_excess.amount.to_string()
+    );
+
+    // a filled order has nothing left to preview
+    assert!(query_preview_execution(
+        deps.as_ref(),
+        mock_env(),
+        &oracle,
+        1u64,
+        EXECUTOR.to_string(),
+    )
+    .is_err());
+}
+
+#[test]
+fn order_responses_include_the_creation_time() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+
+    let res = query_order(deps.as_ref(), 1u64).unwrap();
+    assert_eq!(res.created_at, mock_env().block.time.seconds());
+    assert_eq!(res.expires_at, None);
+}
+
+#[test]
+fn fills_are_retained_when_configured() {
+    let oracle = mock_oracle(Decimal::one());
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    fill_order(&mut deps, &oracle, 1u64, 950);
+    let res = query_fills(deps.as_ref(), PAIR.to_string(), None, None).unwrap();
+    assert!(res.fills.is_empty());
+
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            retain_fills: Some(true),
+            ..instantiate_msg()
+        },
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    fill_order(&mut deps, &oracle, 1u64, 950);
+    fill_order(&mut deps, &oracle, 2u64, 1950);
+
+    // most recent order first
+    let res = query_fills(deps.as_ref(), PAIR.to_string(), None, None).unwrap();
+    assert_eq!(
+        res.fills
+            .iter()
+            .map(|fill| (fill.order.order_id, fill.filled_return.u128()))
+            .collect::<Vec<(u64, u128)>>(),
+        vec![(2u64, 1000u128), (1u64, 950u128)]
+    );
+    assert_eq!(res.fills[0].executor, EXECUTOR);
+    assert_eq!(res.fills[0].filled_at, mock_env().block.time.seconds());
+
+    let res = query_fills(deps.as_ref(), PAIR.to_string(), Some(2u64), None).unwrap();
+    assert_eq!(res.fills.len(), 1);
+    assert_eq!(res.fills[0].order.order_id, 1u64);
+}
+
+#[test]
+fn pair_stats_average_the_fees_of_the_fills() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    assert_eq!(
+        query_pair_stats(deps.as_ref(), PAIR.to_string()).unwrap(),
+        PairStatsResponse {
+            pair_addr: PAIR.to_string(),
+            fill_count: 0u64,
+            average_fee: Uint128::zero(),
+        }
+    );
+
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    submit_token_order(deps.as_mut(), &oracle, 1000, 900, 21);
+    fill_order(&mut deps, &oracle, 1u64, 1000);
+    fill_order(&mut deps, &oracle, 2u64, 2000);
+
+    // the average rounds down
+    assert_eq!(
+        query_pair_stats(deps.as_ref(), PAIR.to_string()).unwrap(),
+        PairStatsResponse {
+            pair_addr: PAIR.to_string(),
+            fill_count: 2u64,
+            average_fee: Uint128::from(15u128),
+        }
+    );
+}
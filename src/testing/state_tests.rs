@@ -0,0 +1,143 @@
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{Decimal, Storage};
+
+use crate::contract::instantiate;
+use crate::msg::OrderBy;
+use crate::order::cancel_order;
+use crate::state::{read_order_range, read_orders, store_order, OrderInfo, ORDERS};
+use crate::testing::mock_querier::mock_dependencies;
+use crate::testing::{instantiate_msg, mock_oracle, submit_token_order, BIDDER};
+
+// pages through every order with the given page size
+fn read_all_orders(storage: &dyn Storage, order_by: OrderBy, limit: u32) -> Vec<u64> {
+    let mut order_ids: Vec<u64> = vec![];
+    loop {
+        let page: Vec<u64> = read_orders(
+            storage,
+            order_ids.last().copied(),
+            Some(limit),
+            Some(order_by.clone()),
+        )
+        .unwrap()
+        .iter()
+        .map(|order| order.order_id)
+        .collect();
+        if page.is_empty() {
+            return order_ids;
+        }
+        order_ids.extend(page);
+    }
+}
+
+#[test]
+fn read_orders_pages_across_removed_ids() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..10 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+    for order_id in (2u64..=10u64).step_by(2) {
+        cancel_order(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(BIDDER, &[]),
+            &oracle,
+            order_id,
+            None,
+            None,
+        )
+        .unwrap();
+    }
+
+    assert_eq!(
+        read_all_orders(&deps.storage, OrderBy::Asc, 2u32),
+        vec![1u64, 3u64, 5u64, 7u64, 9u64]
+    );
+    assert_eq!(
+        read_all_orders(&deps.storage, OrderBy::Desc, 2u32),
+        vec![9u64, 7u64, 5u64, 3u64, 1u64]
+    );
+}
+
+#[test]
+fn read_orders_bounds_at_the_edge_ids() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    submit_token_order(deps.as_mut(), &mock_oracle(Decimal::one()), 1000, 900, 10);
+    let order: OrderInfo = ORDERS.load(&deps.storage, &1u64.to_be_bytes()).unwrap();
+    for order_id in [0u64, u64::MAX] {
+        store_order(
+            &mut deps.storage,
+            &OrderInfo {
+                order_id,
+                ..order.clone()
+            },
+        )
+        .unwrap();
+    }
+
+    let read = |start_after: Option<u64>, order_by: OrderBy| {
+        read_orders(&deps.storage, start_after, None, Some(order_by))
+            .unwrap()
+            .iter()
+            .map(|order| order.order_id)
+            .collect::<Vec<u64>>()
+    };
+
+    assert_eq!(read(None, OrderBy::Asc), vec![0u64, 1u64, u64::MAX]);
+    assert_eq!(read(Some(0u64), OrderBy::Asc), vec![1u64, u64::MAX]);
+    assert_eq!(read(Some(1u64), OrderBy::Asc), vec![u64::MAX]);
+    assert_eq!(read(Some(u64::MAX), OrderBy::Asc), Vec::<u64>::new());
+    assert_eq!(read(None, OrderBy::Desc), vec![u64::MAX, 1u64, 0u64]);
+    assert_eq!(read(Some(u64::MAX), OrderBy::Desc), vec![1u64, 0u64]);
+    assert_eq!(read(Some(1u64), OrderBy::Desc), vec![0u64]);
+    assert_eq!(read(Some(0u64), OrderBy::Desc), Vec::<u64>::new());
+
+    // the range bounds are inclusive
+    assert_eq!(
+        read_order_range(&deps.storage, 0u64, u64::MAX)
+            .unwrap()
+            .iter()
+            .map(|order| order.order_id)
+            .collect::<Vec<u64>>(),
+        vec![0u64, 1u64, u64::MAX]
+    );
+}
+
+#[test]
+fn zero_limit_reads_a_default_page() {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        instantiate_msg(),
+    )
+    .unwrap();
+    let oracle = mock_oracle(Decimal::one());
+    for _ in 0..12 {
+        submit_token_order(deps.as_mut(), &oracle, 1000, 900, 10);
+    }
+
+    let orders = read_orders(&deps.storage, None, Some(0u32), Some(OrderBy::Asc)).unwrap();
+    assert_eq!(orders.len(), 10usize);
+    assert_eq!(
+        orders.len(),
+        read_orders(&deps.storage, None, None, Some(OrderBy::Asc))
+            .unwrap()
+            .len()
+    );
+}